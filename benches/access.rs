@@ -1,3 +1,4 @@
+#![cfg(feature = "nightly")]
 #![feature(test)]
 
 extern crate test;
@@ -10,7 +11,7 @@ use rle_vec::RleVec;
 
 #[bench]
 fn rle_iterate_10_000_unique_values(b: &mut Bencher) {
-    let rle = RleVec::from_iter(0..10_000);
+    let rle: RleVec<_> = RleVec::from_iter(0..10_000);
     b.iter(|| {
         for (i, v) in rle.iter().enumerate() {
             assert_eq!(i, *v);
@@ -30,7 +31,7 @@ fn vec_iterate_10_000_unique_values(b: &mut Bencher) {
 
 #[bench]
 fn rle_iterate_10_000_equal_values(b: &mut Bencher) {
-    let rle = RleVec::from_iter(repeat(0).take(10_000));
+    let rle: RleVec<_> = RleVec::from_iter(repeat(0).take(10_000));
     b.iter(|| {
         for v in rle.iter() {
             assert_eq!(*v, 0);
@@ -54,7 +55,7 @@ fn rle_iterate_1000_runs_of_10_values(b: &mut Bencher) {
     let ones = repeat(1).take(10);
     let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
 
-    let rle = RleVec::from_iter(iter);
+    let rle: RleVec<_> = RleVec::from_iter(iter);
     b.iter(|| {
         for v in rle.iter() {
             assert!(*v == 0 || *v == 1); // ugly
@@ -82,7 +83,7 @@ fn rle_random_access_1000_runs_of_10_values(b: &mut Bencher) {
     let ones = repeat(1).take(10);
     let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
 
-    let rle = RleVec::from_iter(iter);
+    let rle: RleVec<_> = RleVec::from_iter(iter);
     let len = rle.len();
     b.iter(|| {
         let mut i = 5;
@@ -109,3 +110,22 @@ fn vec_random_access_1000_runs_of_10_values(b: &mut Bencher) {
         }
     })
 }
+
+#[bench]
+fn rle_cursor_access_1000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
+
+    let rle: RleVec<_> = RleVec::from_iter(iter);
+    let len = rle.len();
+    b.iter(|| {
+        let mut cursor = rle.cursor(0);
+        let mut i = 5;
+        while i < len {
+            cursor.seek(i);
+            let _ = *cursor.value();
+            i += 10;
+        }
+    })
+}
@@ -1,3 +1,4 @@
+#![cfg(feature = "nightly")]
 #![feature(test)]
 
 extern crate test;
@@ -6,6 +7,7 @@ extern crate rle_vec;
 use std::iter::FromIterator;
 use std::iter::repeat;
 use test::Bencher;
+use std::io::Write;
 use rle_vec::RleVec;
 
 #[bench]
@@ -31,7 +33,7 @@ fn vec_create_10_000_unique_values_from_slice(b: &mut Bencher) {
 #[bench]
 fn rle_create_10_000_unique_values_from_iter(b: &mut Bencher) {
     b.iter(|| {
-        RleVec::from_iter(0..10_000);
+        RleVec::<_>::from_iter(0..10_000);
     })
 }
 
@@ -45,7 +47,7 @@ fn vec_create_10_000_unique_values_from_iter(b: &mut Bencher) {
 #[bench]
 fn rle_create_10_000_equal_values_from_iter(b: &mut Bencher) {
     b.iter(|| {
-        RleVec::from_iter(repeat(0).take(10_000));
+        RleVec::<_>::from_iter(repeat(0).take(10_000));
     })
 }
 
@@ -81,7 +83,7 @@ fn rle_create_1000_runs_of_10_values_from_iter(b: &mut Bencher) {
         let ones = repeat(1).take(10);
         let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
 
-        RleVec::from_iter(iter);
+        RleVec::<_>::from_iter(iter);
     })
 }
 #[bench]
@@ -121,3 +123,36 @@ fn vec_create_1000_runs_of_10_values_from_slice(b: &mut Bencher) {
         Vec::from(&slice[..]);
     })
 }
+
+#[bench]
+fn rle_write_1_000_000_bytes_in_runs_of_1000(b: &mut Bencher) {
+    let bytes: Vec<u8> = (0..1_000_000).map(|i| (i / 1000 % 7) as u8).collect();
+
+    b.iter(|| {
+        let mut rle = RleVec::<u8>::new();
+        rle.write_all(&bytes).unwrap();
+        assert_eq!(rle.runs_len(), 1000);
+    })
+}
+
+#[bench]
+fn rle_extend_1_000_000_bytes_in_runs_of_1000(b: &mut Bencher) {
+    let bytes: Vec<u8> = (0..1_000_000).map(|i| (i / 1000 % 7) as u8).collect();
+
+    b.iter(|| {
+        let mut rle = RleVec::<u8>::new();
+        rle.extend(bytes.iter().cloned());
+        assert_eq!(rle.runs_len(), 1000);
+    })
+}
+
+#[bench]
+fn rle_write_runs_500_000_u16_samples_in_runs_of_500(b: &mut Bencher) {
+    let bytes: Vec<u8> = (0..500_000u32).flat_map(|i| ((i / 500 % 7) as u16).to_le_bytes()).collect();
+
+    b.iter(|| {
+        let mut rle = RleVec::<u16>::new();
+        rle.write_runs(&bytes).unwrap();
+        assert_eq!(rle.runs_len(), 1000);
+    })
+}
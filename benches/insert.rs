@@ -1,3 +1,4 @@
+#![cfg(feature = "nightly")]
 #![feature(test)]
 
 extern crate test;
@@ -6,7 +7,7 @@ extern crate rle_vec;
 use std::iter::FromIterator;
 use std::iter::repeat;
 use test::Bencher;
-use rle_vec::RleVec;
+use rle_vec::{RleVec, ChunkedRleVec};
 
 #[bench]
 fn rle_insert_runmids_non_breaking_1000_runs_of_10_values(b: &mut Bencher) {
@@ -69,3 +70,112 @@ fn vec_insert_runmids_1000_runs_of_10_values(b: &mut Bencher) {
         assert_eq!(vec.len(), 11_000);
     })
 }
+
+#[bench]
+fn chunked_insert_runmids_non_breaking_1000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = ChunkedRleVec::from(&vec[..]);
+        let mut i = 5;
+        let mut val = 0;
+        for _ in 0..1000 {
+            rle.insert(i, val);
+            i += 11;
+            val = if val == 0 { 1 } else { 0 };
+        }
+        assert_eq!(rle.len(), 11_000);
+        assert_eq!(rle.runs_len(), 1000);
+    })
+}
+
+#[bench]
+fn chunked_insert_runmids_breaking_1000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = ChunkedRleVec::from(&vec[..]);
+        let mut i = 5;
+        let mut val = 1;
+        for _ in 0..1000 {
+            rle.insert(i, val);
+            i += 11;
+            val = if val == 0 { 1 } else { 0 };
+        }
+        assert_eq!(rle.len(), 11_000);
+        assert_eq!(rle.runs_len(), 3000);
+    })
+}
+
+#[bench]
+fn rle_insert_runmids_non_breaking_10_000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(100_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = RleVec::from(&vec[..]);
+        let mut i = 5;
+        let mut val = 0;
+        for _ in 0..10_000 {
+            rle.insert(i, val);
+            i += 11;
+            val = if val == 0 { 1 } else { 0 };
+        }
+        assert_eq!(rle.len(), 110_000);
+        assert_eq!(rle.runs_len(), 10_000);
+    })
+}
+
+#[bench]
+fn chunked_insert_runmids_non_breaking_10_000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(100_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = ChunkedRleVec::from(&vec[..]);
+        let mut i = 5;
+        let mut val = 0;
+        for _ in 0..10_000 {
+            rle.insert(i, val);
+            i += 11;
+            val = if val == 0 { 1 } else { 0 };
+        }
+        assert_eq!(rle.len(), 110_000);
+        assert_eq!(rle.runs_len(), 10_000);
+    })
+}
+
+#[bench]
+fn cursor_insert_runmids_non_breaking_10_000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(100_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = RleVec::from(&vec[..]);
+        {
+            let mut cursor = rle.cursor_mut(0);
+            let mut i = 5;
+            let mut val = 0;
+            for _ in 0..10_000 {
+                cursor.seek(i);
+                cursor.insert(val);
+                i += 11;
+                val = if val == 0 { 1 } else { 0 };
+            }
+        }
+        assert_eq!(rle.len(), 110_000);
+        assert_eq!(rle.runs_len(), 10_000);
+    })
+}
@@ -1,3 +1,4 @@
+#![cfg(feature = "nightly")]
 #![feature(test)]
 
 extern crate test;
@@ -6,7 +7,7 @@ extern crate rle_vec;
 use std::iter::FromIterator;
 use std::iter::repeat;
 use test::Bencher;
-use rle_vec::RleVec;
+use rle_vec::{RleVec, ChunkedRleVec};
 
 #[bench]
 fn rle_remove_runmids_non_breaking_1000_runs_of_10_values(b: &mut Bencher) {
@@ -86,3 +87,84 @@ fn vec_remove_runs_merging_1000_runs(b: &mut Bencher) {
     })
 }
 
+#[bench]
+fn chunked_remove_runmids_non_breaking_1000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = ChunkedRleVec::from(&vec[..]);
+        let mut i = 5;
+        let mut val = 0;
+        for _ in 0..1000 {
+            assert_eq!(rle.remove(i), val);
+            i += 9;
+            val = if val == 0 { 1 } else { 0 };
+        }
+        assert_eq!(rle.len(), 9000);
+        assert_eq!(rle.runs_len(), 1000);
+    })
+}
+
+#[bench]
+fn chunked_remove_runs_merging_1000_runs(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(1);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(5_500);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = ChunkedRleVec::from(&vec[..]);
+        let mut i = 10;
+        for _ in 0..500 {
+            assert_eq!(rle.remove(i),1);
+            i += 10;
+        }
+        assert_eq!(rle.len(), 5_000);
+        assert_eq!(rle.runs_len(), 1);
+    })
+}
+
+#[bench]
+fn rle_remove_runmids_non_breaking_10_000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(100_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = RleVec::from(&vec[..]);
+        let mut i = 5;
+        let mut val = 0;
+        for _ in 0..10_000 {
+            assert_eq!(rle.remove(i), val);
+            i += 9;
+            val = if val == 0 { 1 } else { 0 };
+        }
+        assert_eq!(rle.len(), 90_000);
+        assert_eq!(rle.runs_len(), 10_000);
+    })
+}
+
+#[bench]
+fn chunked_remove_runmids_non_breaking_10_000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(100_000);
+    let vec: Vec<_> = iter.collect();
+
+    b.iter(|| {
+        let mut rle = ChunkedRleVec::from(&vec[..]);
+        let mut i = 5;
+        let mut val = 0;
+        for _ in 0..10_000 {
+            assert_eq!(rle.remove(i), val);
+            i += 9;
+            val = if val == 0 { 1 } else { 0 };
+        }
+        assert_eq!(rle.len(), 90_000);
+        assert_eq!(rle.runs_len(), 10_000);
+    })
+}
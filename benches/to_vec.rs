@@ -1,3 +1,4 @@
+#![cfg(feature = "nightly")]
 #![feature(test)]
 
 extern crate test;
@@ -6,7 +7,8 @@ extern crate rle_vec;
 use std::iter::FromIterator;
 use std::iter::repeat;
 use test::Bencher;
-use rle_vec::RleVec;
+use std::io::Read;
+use rle_vec::{RleVec, RleReader};
 
 #[bench]
 fn rle_to_vec_of_u8_10_000_equal_values(b: &mut Bencher) {
@@ -41,7 +43,7 @@ fn rle_to_vec_of_u16_1000_runs_of_10_values(b: &mut Bencher) {
     let ones = repeat(1).take(10);
     let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
 
-    let rle = RleVec::from_iter(iter);
+    let rle: RleVec<_> = RleVec::from_iter(iter);
 
     b.iter(|| {
         let vec = rle.to_vec();
@@ -66,3 +68,16 @@ fn rle_to_vec_of_u64_10_000_equal_values(b: &mut Bencher) {
         assert_eq!(vec.len(), 10_000);
     })
 }
+
+#[bench]
+fn rle_reader_of_u8_1000_runs_of_10_values(b: &mut Bencher) {
+    let zeros = repeat(0).take(10);
+    let ones = repeat(1).take(10);
+    let iter = repeat(zeros.chain(ones)).flat_map(|x| x).take(10_000);
+    let rle = RleVec::<u8>::from_iter(iter);
+    b.iter(|| {
+        let mut vec = Vec::with_capacity(10_000);
+        RleReader::new(&rle).read_to_end(&mut vec).unwrap();
+        assert_eq!(vec.len(), 10_000);
+    })
+}
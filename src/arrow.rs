@@ -0,0 +1,229 @@
+//! Conversions from and to the run-end encoded arrays of Apache Arrow, enabled with the `arrow`
+//! feature.
+//!
+//! A `RunArray` stores the exclusive end of every run in its `run_ends` child, the inclusive
+//! ends of an `RleVec` shifted by one, and the run values in its `values` child. The run ends
+//! are always copied, the values are moved by [`into_run_array`](fn.into_run_array.html).
+//!
+//! Arrow does not require neighbouring runs to hold different values, converting such an array
+//! keeps the runs as they are, use [`RleVec::coalesce`](../struct.RleVec.html#method.coalesce)
+//! to merge them. Null values are not supported.
+//!
+//! # Example
+//! ```
+//! # extern crate arrow_array;
+//! # extern crate rle_vec;
+//! # use arrow_array::{Array, RunArray};
+//! # use arrow_array::types::{Int32Type, UInt8Type};
+//! # use rle_vec::{RleVec, arrow};
+//! # fn main() {
+//! let rle = RleVec::from(&[1u8, 1, 1, 2, 2, 3][..]);
+//!
+//! let array: RunArray<Int32Type> = arrow::to_run_array::<_, UInt8Type, _>(&rle).unwrap();
+//! assert_eq!(array.run_ends().values(), &[3, 5, 6]);
+//!
+//! // slices honour the logical offset and length of the array
+//! let slice = array.slice(2, 3);
+//! let rle: RleVec<u8> = arrow::from_run_array::<UInt8Type, _, _>(&slice).unwrap();
+//! assert_eq!(rle.to_vec(), vec![1, 2, 2]);
+//! # }
+//! ```
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{ArrowPrimitiveType, RunEndIndexType};
+use arrow_array::{Array, ArrayRef, OffsetSizeTrait, PrimitiveArray, RunArray, StringArray};
+use arrow_buffer::{ArrowNativeType, ScalarBuffer};
+use arrow_schema::ArrowError;
+
+use super::{RleIndex, RleVec};
+
+fn run_ends<R: RunEndIndexType, I: RleIndex>(ends: &[I]) -> Result<PrimitiveArray<R>, ArrowError> {
+    let run_ends = ends.iter().map(|&end| {
+        R::Native::from_usize(end.to_usize() + 1).ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!(
+                "the run end {} overflows the run end type {}", end.to_usize() + 1, R::DATA_TYPE))
+        })
+    });
+    let run_ends = run_ends.collect::<Result<Vec<_>, _>>()?;
+    Ok(PrimitiveArray::new(ScalarBuffer::from(run_ends), None))
+}
+
+/// Converts an `RleVec` of primitive values to a `RunArray`.
+///
+/// Returns an error if the length overflows the run end type `R`.
+pub fn to_run_array<R, T, I>(rle: &RleVec<T::Native, I>) -> Result<RunArray<R>, ArrowError>
+    where R: RunEndIndexType, T: ArrowPrimitiveType, I: RleIndex
+{
+    let values = PrimitiveArray::<T>::new(ScalarBuffer::from(rle.values().to_vec()), None);
+    RunArray::try_new(&run_ends(rle.ends())?, &values)
+}
+
+/// Converts an `RleVec` of primitive values to a `RunArray`, moving the values.
+///
+/// Returns an error if the length overflows the run end type `R`.
+pub fn into_run_array<R, T, I>(rle: RleVec<T::Native, I>) -> Result<RunArray<R>, ArrowError>
+    where R: RunEndIndexType, T: ArrowPrimitiveType, I: RleIndex
+{
+    let run_ends = run_ends(rle.ends())?;
+    let (_, values) = rle.into_raw_parts();
+    let values = PrimitiveArray::<T>::new(ScalarBuffer::from(values), None);
+    RunArray::try_new(&run_ends, &values)
+}
+
+/// Converts an `RleVec` of strings to a `RunArray` with `Utf8` values.
+///
+/// Returns an error if the length overflows the run end type `R`.
+///
+/// # Example
+/// ```
+/// # extern crate arrow_array;
+/// # extern crate rle_vec;
+/// # use arrow_array::RunArray;
+/// # use arrow_array::types::Int64Type;
+/// # use rle_vec::{RleVec, arrow};
+/// # fn main() {
+/// let rle: RleVec<&str> = vec!["chr1", "chr1", "chr2"].into_iter().collect();
+///
+/// let array: RunArray<Int64Type> = arrow::strings_to_run_array(&rle).unwrap();
+/// let back: RleVec<String> = arrow::strings_from_run_array(&array).unwrap();
+/// assert_eq!(back.to_vec(), vec!["chr1", "chr1", "chr2"]);
+/// # }
+/// ```
+pub fn strings_to_run_array<R, S, I>(rle: &RleVec<S, I>) -> Result<RunArray<R>, ArrowError>
+    where R: RunEndIndexType, S: AsRef<str>, I: RleIndex
+{
+    let values = StringArray::from_iter_values(rle.values().iter());
+    RunArray::try_new(&run_ends(rle.ends())?, &values)
+}
+
+/// Returns the first physical run and the inclusive ends of the runs in the logical slice.
+fn logical_runs<R: RunEndIndexType, I: RleIndex>(array: &RunArray<R>)
+    -> Result<(usize, Vec<I>), ArrowError>
+{
+    let run_ends = array.run_ends();
+    if run_ends.is_empty() {
+        return Ok((0, Vec::new()))
+    }
+    let (offset, len) = (run_ends.offset(), run_ends.len());
+    let start = run_ends.get_start_physical_index();
+    let end = run_ends.get_end_physical_index();
+    let ends = run_ends.values()[start..=end].iter().map(|&run_end| {
+        let run_end = std::cmp::min(run_end.as_usize(), offset + len) - offset;
+        <I as RleIndex>::from_usize(run_end - 1).ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!("the run end {} overflows the index type", run_end))
+        })
+    });
+    Ok((start, ends.collect::<Result<_, _>>()?))
+}
+
+fn null_error() -> ArrowError {
+    ArrowError::InvalidArgumentError("null values can not be stored in an RleVec".to_string())
+}
+
+/// Converts a `RunArray` with primitive values of type `T` to an `RleVec`.
+///
+/// Returns an error if the values are not of type `T`, if a value in the logical slice of
+/// the array is null or if the length overflows the index type `I`.
+pub fn from_run_array<T, R, I>(array: &RunArray<R>) -> Result<RleVec<T::Native, I>, ArrowError>
+    where T: ArrowPrimitiveType, R: RunEndIndexType, I: RleIndex
+{
+    let values = array.values().as_primitive_opt::<T>().ok_or_else(|| {
+        ArrowError::InvalidArgumentError(format!(
+            "expected values of type {}, found {}", T::DATA_TYPE, array.values().data_type()))
+    })?;
+    let (start, ends) = logical_runs(array)?;
+    let values = values.slice(start, ends.len());
+    if values.null_count() > 0 {
+        return Err(null_error())
+    }
+    let values = values.values().to_vec();
+    RleVec::from_ends(ends, values).map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))
+}
+
+fn strings<O: OffsetSizeTrait>(values: &ArrayRef, start: usize, len: usize)
+    -> Option<Result<Vec<String>, ArrowError>>
+{
+    let values = values.as_string_opt::<O>()?.slice(start, len);
+    Some(values.iter().map(|value| value.map(str::to_string).ok_or_else(null_error)).collect())
+}
+
+/// Converts a `RunArray` with `Utf8` or `LargeUtf8` values to an `RleVec` of strings.
+///
+/// Returns an error for other value types, if a value in the logical slice of the array is
+/// null or if the length overflows the index type `I`.
+pub fn strings_from_run_array<R, I>(array: &RunArray<R>) -> Result<RleVec<String, I>, ArrowError>
+    where R: RunEndIndexType, I: RleIndex
+{
+    let (start, ends) = logical_runs(array)?;
+    let values = strings::<i32>(array.values(), start, ends.len())
+        .or_else(|| strings::<i64>(array.values(), start, ends.len()))
+        .ok_or_else(|| ArrowError::InvalidArgumentError(format!(
+            "expected string values, found {}", array.values().data_type())))??;
+    RleVec::from_ends(ends, values).map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::types::{Float64Type, Int16Type, Int32Type, Int64Type, UInt32Type};
+    use arrow_array::LargeStringArray;
+    use super::*;
+
+    #[test]
+    fn primitive_roundtrips() {
+        let rle: RleVec<u32, u16> = vec![4, 4, 4, 1, 9, 9].into_iter().collect();
+        let array = to_run_array::<Int32Type, UInt32Type, _>(&rle).unwrap();
+        assert_eq!(array.len(), 6);
+        assert_eq!(array.run_ends().values(), &[3, 4, 6]);
+        assert_eq!(from_run_array::<UInt32Type, _, u16>(&array).unwrap(), rle);
+
+        let array = into_run_array::<Int64Type, UInt32Type, _>(rle.clone()).unwrap();
+        assert_eq!(from_run_array::<UInt32Type, _, u16>(&array).unwrap(), rle);
+
+        for offset in 0..6 {
+            for len in 0..6 - offset {
+                let slice = array.slice(offset, len);
+                let expected = &rle.to_vec()[offset..offset + len];
+                let back = from_run_array::<UInt32Type, _, usize>(&slice).unwrap();
+                assert_eq!(back.to_vec(), expected);
+            }
+        }
+
+        // floats are not Eq, the runs are copied as they are
+        let ends = vec![1usize, 2];
+        let rle = RleVec::from_ends(ends, vec![0.5f64, 0.5]).unwrap();
+        let array = to_run_array::<Int16Type, Float64Type, _>(&rle).unwrap();
+        let back = from_run_array::<Float64Type, _, usize>(&array).unwrap();
+        assert_eq!(back.into_raw_parts(), (vec![1, 2], vec![0.5, 0.5]));
+    }
+
+    #[test]
+    fn string_roundtrips() {
+        let rle: RleVec<String> = ["a", "a", "bc", "", ""].iter().map(|s| s.to_string()).collect();
+        let array = strings_to_run_array::<Int32Type, _, _>(&rle).unwrap();
+        assert_eq!(strings_from_run_array::<_, usize>(&array).unwrap(), rle);
+        let slice = array.slice(1, 2);
+        assert_eq!(strings_from_run_array::<_, usize>(&slice).unwrap().to_vec(), vec!["a", "bc"]);
+
+        let values = LargeStringArray::from(vec!["x", "y"]);
+        let run_ends = PrimitiveArray::<Int64Type>::from(vec![2, 3]);
+        let array = RunArray::try_new(&run_ends, &values).unwrap();
+        assert_eq!(strings_from_run_array::<_, usize>(&array).unwrap().to_vec(), vec!["x", "x", "y"]);
+    }
+
+    #[test]
+    fn conversion_errors() {
+        let rle: RleVec<u32> = vec![7; 70_000].into_iter().collect();
+        assert!(to_run_array::<Int16Type, UInt32Type, _>(&rle).is_err());
+        let array = to_run_array::<Int32Type, UInt32Type, _>(&rle).unwrap();
+        assert!(from_run_array::<UInt32Type, _, u16>(&array).is_err());
+        assert!(from_run_array::<UInt32Type, _, u16>(&array.slice(0, 65_536)).is_ok());
+        assert!(from_run_array::<Int64Type, _, usize>(&array).is_err());
+        assert!(strings_from_run_array::<_, usize>(&array).is_err());
+
+        let values = StringArray::from(vec![Some("x"), None]);
+        let run_ends = PrimitiveArray::<Int32Type>::from(vec![2, 3]);
+        let array = RunArray::try_new(&run_ends, &values).unwrap();
+        assert!(strings_from_run_array::<_, usize>(&array).is_err());
+        assert_eq!(strings_from_run_array::<_, usize>(&array.slice(0, 2)).unwrap().to_vec(), vec!["x", "x"]);
+    }
+}
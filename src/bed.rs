@@ -0,0 +1,323 @@
+//! Reading and writing genomic tracks as bedGraph and BED intervals.
+//!
+//! A bedGraph line `chrom start end value` sets the positions `start..end` of a chromosome to
+//! `value`, a BED line `chrom start end ...` marks them in a mask. Coordinates are zero-based
+//! and half-open, every chromosome becomes its own `RleVec` starting at position zero.
+//!
+//! Empty lines, comments starting with `#` and `track` or `browser` lines are skipped when
+//! reading. Errors report the one-based line number.
+//!
+//! # Example
+//! ```
+//! # use rle_vec::{RleVec, bed};
+//! let coverage: RleVec<u32> = vec![0, 0, 3, 3, 3, 1].into_iter().collect();
+//!
+//! let mut out = Vec::new();
+//! bed::write_bedgraph(&coverage, "chr1", Some(&0), &mut out).unwrap();
+//! assert_eq!(out, b"chr1\t2\t5\t3\nchr1\t5\t6\t1\n");
+//!
+//! let tracks = bed::read_bedgraph::<_, u32, usize>(&out[..], 0).unwrap();
+//! assert_eq!(tracks, vec![("chr1".to_string(), coverage)]);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use super::{RleIndex, RleVec};
+
+/// The name and the `RleVec` of every chromosome, in the order the chromosomes first appear.
+pub type Tracks<T, I = usize> = Vec<(String, RleVec<T, I>)>;
+
+/// The error returned when reading a bedGraph or BED file fails.
+#[derive(Debug)]
+pub enum BedError {
+    /// Reading failed.
+    Io(io::Error),
+    /// The line has fewer fields than required.
+    MissingField {
+        /// The line number, starting at one.
+        line: usize,
+    },
+    /// The start or end of the interval is not a valid coordinate.
+    InvalidCoordinate {
+        /// The line number, starting at one.
+        line: usize,
+    },
+    /// The end of the interval is not after its start.
+    EmptyInterval {
+        /// The line number, starting at one.
+        line: usize,
+    },
+    /// The value of the bedGraph line could not be parsed.
+    InvalidValue {
+        /// The line number, starting at one.
+        line: usize,
+    },
+    /// The interval starts before the end of the previous interval of its chromosome in a
+    /// bedGraph file, or before the start of the previous interval in a BED file.
+    Unsorted {
+        /// The line number, starting at one.
+        line: usize,
+    },
+    /// The end of the interval overflows the index type.
+    IndexOverflow {
+        /// The line number, starting at one.
+        line: usize,
+    },
+}
+
+impl fmt::Display for BedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BedError::Io(ref e) => write!(f, "reading the intervals failed: {}", e),
+            BedError::MissingField { line } => write!(f, "line {}: missing field", line),
+            BedError::InvalidCoordinate { line } => write!(f, "line {}: invalid coordinate", line),
+            BedError::EmptyInterval { line } => write!(f, "line {}: the end is not after the start", line),
+            BedError::InvalidValue { line } => write!(f, "line {}: invalid value", line),
+            BedError::Unsorted { line } => write!(f, "line {}: the intervals are not sorted", line),
+            BedError::IndexOverflow { line } => write!(f, "line {}: the end overflows the index type", line),
+        }
+    }
+}
+
+impl Error for BedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            BedError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BedError {
+    fn from(e: io::Error) -> Self {
+        BedError::Io(e)
+    }
+}
+
+/// Writes the runs of `rle` as bedGraph lines of the chromosome `chrom`.
+///
+/// Runs with the value `skip` are left out, like the zero coverage between reads.
+pub fn write_bedgraph<T, I, W>(rle: &RleVec<T, I>, chrom: &str, skip: Option<&T>, mut writer: W)
+    -> io::Result<()>
+    where T: fmt::Display + PartialEq, I: RleIndex, W: Write
+{
+    let mut start = 0;
+    for run in rle.runs() {
+        if skip != Some(run.value) {
+            writeln!(writer, "{}\t{}\t{}\t{}", chrom, start, start + run.len, run.value)?;
+        }
+        start += run.len;
+    }
+    Ok(())
+}
+
+/// Writes the `true` runs of `mask` as BED lines of the chromosome `chrom`.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, bed};
+/// let mask = RleVec::from(&[false, true, true, false, true][..]);
+///
+/// let mut out = Vec::new();
+/// bed::write_bed(&mask, "chrX", &mut out).unwrap();
+/// assert_eq!(out, b"chrX\t1\t3\nchrX\t4\t5\n");
+/// ```
+pub fn write_bed<I: RleIndex, W: Write>(mask: &RleVec<bool, I>, chrom: &str, mut writer: W) -> io::Result<()> {
+    let mut start = 0;
+    for run in mask.runs() {
+        if *run.value {
+            writeln!(writer, "{}\t{}\t{}", chrom, start, start + run.len)?;
+        }
+        start += run.len;
+    }
+    Ok(())
+}
+
+/// An interval line with its fields after the coordinates.
+struct Interval<'a> {
+    chrom: &'a str,
+    start: usize,
+    end: usize,
+    rest: ::std::str::SplitWhitespace<'a>,
+}
+
+fn parse_interval(text: &str, line: usize) -> Result<Option<Interval<'_>>, BedError> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("track")
+        || trimmed.starts_with("browser")
+    {
+        return Ok(None)
+    }
+    let mut fields = trimmed.split_whitespace();
+    let chrom = fields.next().ok_or(BedError::MissingField { line })?;
+    let mut coordinate = || -> Result<usize, BedError> {
+        let field = fields.next().ok_or(BedError::MissingField { line })?;
+        field.parse().map_err(|_| BedError::InvalidCoordinate { line })
+    };
+    let (start, end) = (coordinate()?, coordinate()?);
+    if end <= start {
+        return Err(BedError::EmptyInterval { line })
+    }
+    Ok(Some(Interval { chrom, start, end, rest: fields }))
+}
+
+/// Collects the intervals of every chromosome in the order the chromosomes first appear.
+fn read_intervals<R, T, I, F>(reader: R, mut add: F) -> Result<Tracks<T, I>, BedError>
+    where R: BufRead, F: FnMut(&mut RleVec<T, I>, Interval, usize) -> Result<(), BedError>
+{
+    let mut tracks: Tracks<T, I> = Vec::new();
+    let mut positions = HashMap::new();
+    for (i, text) in reader.lines().enumerate() {
+        let text = text?;
+        let line = i + 1;
+        let interval = match parse_interval(&text, line)? {
+            Some(interval) => interval,
+            None => continue,
+        };
+        let position = *positions.entry(interval.chrom.to_string()).or_insert_with(|| {
+            tracks.push((interval.chrom.to_string(), RleVec::default()));
+            tracks.len() - 1
+        });
+        add(&mut tracks[position].1, interval, line)?;
+    }
+    Ok(tracks)
+}
+
+/// Reads the bedGraph lines of every chromosome, in the order the chromosomes first appear.
+///
+/// The intervals of a chromosome must be sorted and may not overlap, the positions between
+/// them are set to `fill`. Every `RleVec` ends at the end of the last interval of its
+/// chromosome.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, bed};
+/// let text = "track type=bedGraph\nchr1 2 4 7\nchr1 5 6 1\n";
+/// let tracks = bed::read_bedgraph::<_, u32, usize>(text.as_bytes(), 0).unwrap();
+/// assert_eq!(tracks[0].1.to_vec(), vec![0, 0, 7, 7, 0, 1]);
+///
+/// let unsorted = "track type=bedGraph\nchr1 2 4 7\nchr1 5 6 1\nchr1 3 4 1\n";
+/// let error = bed::read_bedgraph::<_, u32, usize>(unsorted.as_bytes(), 0).unwrap_err();
+/// assert_eq!(error.to_string(), "line 4: the intervals are not sorted");
+/// ```
+pub fn read_bedgraph<R, T, I>(reader: R, fill: T) -> Result<Tracks<T, I>, BedError>
+    where R: BufRead, T: FromStr + Eq + Clone, I: RleIndex
+{
+    read_intervals(reader, |rle, mut interval, line| {
+        if interval.start < rle.len() {
+            return Err(BedError::Unsorted { line })
+        }
+        let field = interval.rest.next().ok_or(BedError::MissingField { line })?;
+        let value = field.parse().map_err(|_| BedError::InvalidValue { line })?;
+        let gap = interval.start - rle.len();
+        rle.try_push_n(gap, fill.clone())
+            .and_then(|_| rle.try_push_n(interval.end - interval.start, value))
+            .map_err(|_| BedError::IndexOverflow { line })
+    })
+}
+
+/// Reads the BED lines of every chromosome as masks, in the order the chromosomes first
+/// appear.
+///
+/// The intervals of a chromosome must be sorted by their start and may overlap. Every mask
+/// ends at the largest end of the intervals of its chromosome.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, bed};
+/// let text = "chr1\t0\t2\tgeneA\nchr2\t1\t2\tgeneB\nchr1\t1\t3\tgeneC\nchr1\t5\t6\n";
+/// let masks = bed::read_bed::<_, usize>(text.as_bytes()).unwrap();
+///
+/// assert_eq!(masks[0].0, "chr1");
+/// assert_eq!(masks[0].1.to_vec(), vec![true, true, true, false, false, true]);
+/// assert_eq!(masks[1].1.to_vec(), vec![false, true]);
+/// ```
+pub fn read_bed<R: BufRead, I: RleIndex>(reader: R) -> Result<Tracks<bool, I>, BedError> {
+    // the start of the previous interval of every chromosome
+    let mut starts = HashMap::new();
+    read_intervals(reader, |mask, interval, line| {
+        let previous = starts.entry(interval.chrom.to_string()).or_insert(0);
+        if interval.start < *previous {
+            return Err(BedError::Unsorted { line })
+        }
+        *previous = interval.start;
+
+        let len = mask.len();
+        let result = if interval.start >= len {
+            mask.try_push_n(interval.start - len, false)
+                .and_then(|_| mask.try_push_n(interval.end - interval.start, true))
+        } else {
+            // overlaps the previous interval, which ends the mask
+            mask.try_push_n(interval.end.saturating_sub(len), true)
+        };
+        result.map_err(|_| BedError::IndexOverflow { line })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bedgraph_roundtrips() {
+        let chr1: RleVec<u32, u32> = vec![0, 0, 5, 5, 0, 2, 2, 2].into_iter().collect();
+        let chr2: RleVec<u32, u32> = vec![1, 1, 1].into_iter().collect();
+        let mut out = Vec::new();
+        write_bedgraph(&chr1, "chr1", None, &mut out).unwrap();
+        write_bedgraph(&chr2, "chr2", Some(&0), &mut out).unwrap();
+        let text = String::from_utf8(out.clone()).unwrap();
+        assert_eq!(text, "chr1\t0\t2\t0\nchr1\t2\t4\t5\nchr1\t4\t5\t0\nchr1\t5\t8\t2\nchr2\t0\t3\t1\n");
+
+        let tracks = read_bedgraph::<_, u32, u32>(&out[..], 9).unwrap();
+        assert_eq!(tracks, vec![("chr1".to_string(), chr1.clone()), ("chr2".to_string(), chr2)]);
+
+        // skipped runs are filled in again
+        let mut out = Vec::new();
+        write_bedgraph(&chr1, "chr1", Some(&0), &mut out).unwrap();
+        let tracks = read_bedgraph::<_, u32, u32>(&out[..], 0).unwrap();
+        assert_eq!(tracks[0].1, chr1.iter().cloned().take(8).collect());
+
+        // chromosomes may continue after other chromosomes
+        let text = "# comment\n\nbrowser position chr1\nchr1 0 1 4\nchr2 0 1 4\nchr1 1 2 4\n";
+        let tracks = read_bedgraph::<_, u32, usize>(text.as_bytes(), 0).unwrap();
+        assert_eq!(tracks[0].1.runs_len(), 1);
+        assert_eq!(tracks[0].1.len(), 2);
+    }
+
+    #[test]
+    fn bed_roundtrips() {
+        let mask: RleVec<bool> = vec![false, true, true, false, false, true].into_iter().collect();
+        let mut out = Vec::new();
+        write_bed(&mask, "chr3", &mut out).unwrap();
+        let masks = read_bed::<_, usize>(&out[..]).unwrap();
+        assert_eq!(masks, vec![("chr3".to_string(), mask)]);
+
+        let masks = read_bed::<_, usize>("chr1 0 4\nchr1 1 2\nchr1 4 5\n".as_bytes()).unwrap();
+        assert_eq!(masks[0].1.to_vec(), vec![true; 5]);
+        assert_eq!(masks[0].1.runs_len(), 1);
+    }
+
+    #[test]
+    fn line_numbered_errors() {
+        fn bedgraph(text: &str) -> String {
+            read_bedgraph::<_, u8, u16>(text.as_bytes(), 0).unwrap_err().to_string()
+        }
+        assert_eq!(bedgraph("track\nchr1 0 1 1\nchr1 1 2\n"), "line 3: missing field");
+        assert_eq!(bedgraph("chr1 0\n"), "line 1: missing field");
+        assert_eq!(bedgraph("\n\nchr1 x 2 1\n"), "line 3: invalid coordinate");
+        assert_eq!(bedgraph("chr1 -1 2 1\n"), "line 1: invalid coordinate");
+        assert_eq!(bedgraph("chr1 2 2 1\n"), "line 1: the end is not after the start");
+        assert_eq!(bedgraph("chr1 0 2 300\n"), "line 1: invalid value");
+        assert_eq!(bedgraph("chr1 0 2 1\nchr1 1 3 1\n"), "line 2: the intervals are not sorted");
+        assert_eq!(bedgraph("chr1 0 70000 1\n"), "line 1: the end overflows the index type");
+
+        let error = read_bed::<_, usize>("chr1 5 6\nchr1 4 8\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, BedError::Unsorted { line: 2 }));
+        let error = read_bed::<_, usize>(&[b'c', 0xff, b'\n'][..]).unwrap_err();
+        assert!(matches!(error, BedError::Io(_)));
+    }
+}
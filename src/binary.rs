@@ -0,0 +1,556 @@
+//! A versioned binary file format for `RleVec`.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use super::{InvalidRuns, RleIndex, RleVec};
+
+const MAGIC: &[u8; 4] = b"RLEV";
+const VERSION: u8 = 1;
+// limits the preallocation for an untrusted run count
+const MAX_PREALLOCATED_RUNS: usize = 4096;
+
+/// Encodes the values of an `RleVec` for [`write_to`](struct.RleVec.html#method.write_to) and
+/// decodes them for [`read_from`](struct.RleVec.html#method.read_from).
+///
+/// Unsigned integers are stored as LEB128 varints, signed integers are zigzag encoded first.
+/// `u8`, `i8` and `bool` take a single byte and a `Vec<u8>` is stored as its length followed by
+/// the bytes.
+///
+/// # Example
+/// ```
+/// # use std::io::{self, Read, Write};
+/// # use rle_vec::{RleVec, RleValueCodec, FormatError};
+/// #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// enum Base { A, C, G, T }
+///
+/// impl RleValueCodec for Base {
+///     const TYPE_TAG: u8 = 200;
+///
+///     fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+///         writer.write_all(&[*self as u8])
+///     }
+///
+///     fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+///         let mut byte = [0];
+///         reader.read_exact(&mut byte)?;
+///         [Base::A, Base::C, Base::G, Base::T].get(byte[0] as usize).cloned()
+///             .ok_or(FormatError::InvalidValue)
+///     }
+/// }
+///
+/// let rle: RleVec<_> = vec![Base::A, Base::A, Base::T].into_iter().collect();
+/// let mut file = Vec::new();
+/// rle.write_to(&mut file).unwrap();
+/// assert_eq!(RleVec::read_from(&file[..]).unwrap(), rle);
+/// ```
+pub trait RleValueCodec: Sized {
+    /// Identifies the value type in the file header. Tags below 128 are reserved for the
+    /// implementations of this crate.
+    const TYPE_TAG: u8;
+
+    /// Writes the value.
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Reads a value written by `encode`.
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError>;
+}
+
+/// The error returned when reading an `RleVec` in the binary format fails.
+#[derive(Debug)]
+pub enum FormatError {
+    /// Reading from the underlying reader failed.
+    Io(io::Error),
+    /// The input ended before the end of the file.
+    Truncated,
+    /// The input does not start with the magic bytes.
+    BadMagic,
+    /// The file was written in a version of the format this crate can not read.
+    UnsupportedVersion(u8),
+    /// The file stores a different value type than requested.
+    ValueTypeMismatch {
+        /// The type tag of the requested value type.
+        expected: u8,
+        /// The type tag found in the file.
+        found: u8,
+    },
+    /// A varint is longer than 64 bits.
+    InvalidVarint,
+    /// A value could not be decoded or is out of range for its type.
+    InvalidValue,
+    /// The checksum stored in the file does not match its contents.
+    ChecksumMismatch {
+        /// The checksum computed over the contents.
+        expected: u32,
+        /// The checksum stored in the file.
+        found: u32,
+    },
+    /// The element count in the header differs from the sum of the run lengths.
+    LengthMismatch {
+        /// The element count in the header.
+        header: usize,
+        /// The sum of the run lengths.
+        runs: usize,
+    },
+    /// The runs are inconsistent or do not fit the index type.
+    InvalidRuns(InvalidRuns),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::Io(ref e) => write!(f, "i/o error: {}", e),
+            FormatError::Truncated => f.write_str("the input is truncated"),
+            FormatError::BadMagic => f.write_str("the input is not an RleVec file"),
+            FormatError::UnsupportedVersion(version) => write!(f, "unsupported format version {}", version),
+            FormatError::ValueTypeMismatch { expected, found } => {
+                write!(f, "expected values with type tag {} but found {}", expected, found)
+            }
+            FormatError::InvalidVarint => f.write_str("a varint overflows 64 bits"),
+            FormatError::InvalidValue => f.write_str("a value can not be decoded"),
+            FormatError::ChecksumMismatch { expected, found } => {
+                write!(f, "checksum mismatch, computed {:08x} but found {:08x}", expected, found)
+            }
+            FormatError::LengthMismatch { header, runs } => {
+                write!(f, "the header stores {} elements but the runs hold {}", header, runs)
+            }
+            FormatError::InvalidRuns(ref e) => write!(f, "invalid runs: {}", e),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FormatError::Io(ref e) => Some(e),
+            FormatError::InvalidRuns(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => FormatError::Truncated,
+            _ => FormatError::Io(error),
+        }
+    }
+}
+
+impl From<InvalidRuns> for FormatError {
+    fn from(error: InvalidRuns) -> Self {
+        FormatError::InvalidRuns(error)
+    }
+}
+
+pub(crate) fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buf = [0; 10];
+    let mut i = 0;
+    while value >= 0x80 {
+        buf[i] = value as u8 | 0x80;
+        value >>= 7;
+        i += 1;
+    }
+    buf[i] = value as u8;
+    writer.write_all(&buf[..=i])
+}
+
+pub(crate) fn read_varint<R: Read>(reader: &mut R) -> Result<u64, FormatError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        let bits = u64::from(byte[0] & 0x7f);
+        if shift == 63 && bits > 1 {
+            return Err(FormatError::InvalidVarint)
+        }
+        value |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value)
+        }
+    }
+    Err(FormatError::InvalidVarint)
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> Result<usize, FormatError> {
+    usize::try_from(read_varint(reader)?).map_err(|_| InvalidRuns::IndexOverflow.into())
+}
+
+macro_rules! impl_unsigned_codec {
+    ($($t:ty => $tag:expr),*) => {
+        $(
+            impl RleValueCodec for $t {
+                const TYPE_TAG: u8 = $tag;
+
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    write_varint(writer, *self as u64)
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+                    <$t>::try_from(read_varint(reader)?).map_err(|_| FormatError::InvalidValue)
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_signed_codec {
+    ($($t:ty => $tag:expr),*) => {
+        $(
+            impl RleValueCodec for $t {
+                const TYPE_TAG: u8 = $tag;
+
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    let value = *self as i64;
+                    write_varint(writer, ((value << 1) ^ (value >> 63)) as u64)
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+                    let zigzag = read_varint(reader)?;
+                    let value = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+                    <$t>::try_from(value).map_err(|_| FormatError::InvalidValue)
+                }
+            }
+        )*
+    }
+}
+
+impl_unsigned_codec!(u16 => 2, u32 => 3, u64 => 4, usize => 4);
+impl_signed_codec!(i16 => 6, i32 => 7, i64 => 8, isize => 8);
+
+impl RleValueCodec for u8 {
+    const TYPE_TAG: u8 = 1;
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[*self])
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+}
+
+impl RleValueCodec for i8 {
+    const TYPE_TAG: u8 = 5;
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[*self as u8])
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        u8::decode(reader).map(|byte| byte as i8)
+    }
+}
+
+impl RleValueCodec for bool {
+    const TYPE_TAG: u8 = 9;
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[*self as u8])
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(FormatError::InvalidValue),
+        }
+    }
+}
+
+impl RleValueCodec for Vec<u8> {
+    const TYPE_TAG: u8 = 10;
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        writer.write_all(self)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let len = read_varint(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < len {
+            return Err(FormatError::Truncated)
+        }
+        Ok(bytes)
+    }
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// the CRC-32 used by zlib and png
+#[derive(Clone, Copy)]
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Crc32(!0)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = CRC_TABLE[((self.0 ^ u32::from(byte)) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+struct ChecksumWriter<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<T: RleValueCodec, I: RleIndex> RleVec<T, I> {
+    /// Writes the vector in a versioned binary format.
+    ///
+    /// All numbers are little-endian. A file consists of
+    ///
+    /// | field         | encoding                                   |
+    /// |---------------|--------------------------------------------|
+    /// | magic         | the four bytes `RLEV`                      |
+    /// | version       | one byte, currently 1                      |
+    /// | value type    | one byte, the `TYPE_TAG` of the value type |
+    /// | element count | LEB128                                     |
+    /// | run count     | LEB128                                     |
+    /// | run lengths   | LEB128, one per run                        |
+    /// | values        | one per run, see `RleValueCodec`           |
+    /// | checksum      | CRC-32 of everything before it, 4 bytes    |
+    ///
+    /// The writer receives many small writes, wrap it in a `BufWriter` when writing to a file.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let rle = RleVec::from(&[0u32, 0, 0, 300][..]);
+    /// let mut file = Vec::new();
+    /// rle.write_to(&mut file).unwrap();
+    ///
+    /// assert_eq!(&file[..4], b"RLEV");
+    /// assert_eq!(file.len(), 4 + 1 + 1 + 1 + 1 + 2 + 3 + 4);
+    /// ```
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = ChecksumWriter { inner: writer, crc: Crc32::new() };
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, T::TYPE_TAG])?;
+        write_varint(&mut writer, self.len() as u64)?;
+        write_varint(&mut writer, self.runs_len() as u64)?;
+        for run in self.runs() {
+            write_varint(&mut writer, run.len as u64)?;
+        }
+        for value in &self.values {
+            value.encode(&mut writer)?;
+        }
+        let checksum = writer.crc.finish();
+        writer.inner.write_all(&checksum.to_le_bytes())
+    }
+
+    /// Reads a vector written by [`write_to`](#method.write_to).
+    ///
+    /// The reader receives many small reads, wrap it in a `BufReader` when reading from a file.
+    /// Adjacent runs with equal values, as written for a vector with a split run, are merged.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleVec, FormatError};
+    /// let rle = RleVec::from(&[true, true, false][..]);
+    /// let mut file = Vec::new();
+    /// rle.write_to(&mut file).unwrap();
+    ///
+    /// assert_eq!(RleVec::read_from(&file[..]).unwrap(), rle);
+    /// match RleVec::<bool>::read_from(&file[..file.len() - 1]) {
+    ///     Err(FormatError::Truncated) => (),
+    ///     _ => panic!("expected a truncation error"),
+    /// }
+    /// ```
+    pub fn read_from<R: Read>(reader: R) -> Result<RleVec<T, I>, FormatError>
+        where T: Eq
+    {
+        let mut reader = ChecksumReader { inner: reader, crc: Crc32::new() };
+        let mut header = [0; 6];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(FormatError::BadMagic)
+        }
+        if header[4] != VERSION {
+            return Err(FormatError::UnsupportedVersion(header[4]))
+        }
+        if header[5] != T::TYPE_TAG {
+            return Err(FormatError::ValueTypeMismatch { expected: T::TYPE_TAG, found: header[5] })
+        }
+
+        let len = read_usize(&mut reader)?;
+        let runs = read_usize(&mut reader)?;
+        let mut lengths = Vec::with_capacity(runs.min(MAX_PREALLOCATED_RUNS));
+        for _ in 0..runs {
+            lengths.push(read_usize(&mut reader)?);
+        }
+        let mut values = Vec::with_capacity(runs.min(MAX_PREALLOCATED_RUNS));
+        for _ in 0..runs {
+            values.push(T::decode(&mut reader)?);
+        }
+
+        let expected = reader.crc.finish();
+        let mut checksum = [0; 4];
+        reader.inner.read_exact(&mut checksum)?;
+        let found = u32::from_le_bytes(checksum);
+        if expected != found {
+            return Err(FormatError::ChecksumMismatch { expected, found })
+        }
+
+        let mut rle = RleVec::from_run_lengths(&lengths, values)?;
+        if rle.len() != len {
+            return Err(FormatError::LengthMismatch { header: len, runs: rle.len() })
+        }
+        rle.coalesce();
+        Ok(rle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip<T: RleValueCodec + Eq + Clone + fmt::Debug>(values: Vec<T>) {
+        let rle: RleVec<T> = values.into_iter().collect();
+        let mut file = Vec::new();
+        rle.write_to(&mut file).unwrap();
+        assert_eq!(RleVec::<T>::read_from(&file[..]).unwrap(), rle);
+
+        for end in 0..file.len() {
+            match RleVec::<T>::read_from(&file[..end]) {
+                Err(FormatError::Truncated) => (),
+                other => panic!("{:?} for {} of {} bytes", other, end, file.len()),
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrips() {
+        roundtrip(vec![0u8, 0, 255, 255, 255, 1]);
+        roundtrip(vec![1u16, 1, 65_535]);
+        roundtrip(vec![u32::MAX, 0, 0]);
+        roundtrip(vec![u64::MAX, u64::MAX, 1 << 63, 0]);
+        roundtrip(vec![0usize, 0, 300]);
+        roundtrip(vec![-1i8, -128, 127]);
+        roundtrip(vec![i16::MIN, i16::MIN, 0, i16::MAX]);
+        roundtrip(vec![-1i32, -1, 1, 1]);
+        roundtrip(vec![i64::MIN, i64::MAX, 0, -1]);
+        roundtrip(vec![-3isize; 10]);
+        roundtrip(vec![true, true, false]);
+        roundtrip(vec![b"abc".to_vec(), b"abc".to_vec(), vec![], b"\0".to_vec()]);
+        roundtrip(Vec::<u8>::new());
+    }
+
+    #[test]
+    fn varints() {
+        for &value in &[0, 1, 127, 128, 300, 1 << 35, u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value).unwrap();
+            assert_eq!(read_varint(&mut &buf[..]).unwrap(), value);
+        }
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 300).unwrap();
+        assert_eq!(buf, vec![0xac, 0x02]);
+
+        let overflow = [0xff; 10];
+        match read_varint(&mut &overflow[..]) {
+            Err(FormatError::InvalidVarint) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn corrupt_files() {
+        let rle = RleVec::from(&[1u16, 1, 2, 3, 3][..]);
+        let mut file = Vec::new();
+        rle.write_to(&mut file).unwrap();
+        assert_eq!(Crc32::new().finish(), 0);
+
+        // the check value of CRC-32
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+
+        let mut corrupt = file.clone();
+        corrupt[0] = b'X';
+        assert!(matches!(RleVec::<u16>::read_from(&corrupt[..]), Err(FormatError::BadMagic)));
+
+        let mut corrupt = file.clone();
+        corrupt[4] = 2;
+        assert!(matches!(RleVec::<u16>::read_from(&corrupt[..]), Err(FormatError::UnsupportedVersion(2))));
+
+        assert!(matches!(RleVec::<u32>::read_from(&file[..]),
+            Err(FormatError::ValueTypeMismatch { expected: 3, found: 2 })));
+
+        let mut corrupt = file.clone();
+        let last_value = file.len() - 5;
+        corrupt[last_value] = 4;
+        assert!(matches!(RleVec::<u16>::read_from(&corrupt[..]), Err(FormatError::ChecksumMismatch { .. })));
+
+        // a consistent file that does not fit the index type
+        let big = RleVec::from(&[0u8; 70_000][..]);
+        let mut file = Vec::new();
+        big.write_to(&mut file).unwrap();
+        assert!(matches!(RleVec::<u8, u16>::read_from(&file[..]),
+            Err(FormatError::InvalidRuns(InvalidRuns::IndexOverflow))));
+
+        // adjacent equal runs are merged
+        let mut split = RleVec::from(&[1u16, 1, 1, 2][..]);
+        split.cursor_mut(1).split_run_here();
+        let mut file = Vec::new();
+        split.write_to(&mut file).unwrap();
+        let rle = RleVec::<u16>::read_from(&file[..]).unwrap();
+        assert_eq!(rle.ends(), &[2, 3]);
+        assert_eq!(rle.values(), &[1, 2]);
+    }
+}
@@ -0,0 +1,443 @@
+//! The RLE8 and RLE4 compression of BMP images.
+//!
+//! The pixel rows are color table indices, in the order they are stored in the file, which is
+//! bottom-up for a bitmap with a positive height. RLE4 rows hold indices below 16.
+//!
+//! A compressed bitmap is a sequence of two byte packets. A packet `n, v` with `n > 0` repeats
+//! the pixel `v` `n` times, for RLE4 `v` holds two pixels that alternate. A packet starting
+//! with zero is an escape:
+//!
+//! * `0, 0`: end of line
+//! * `0, 1`: end of bitmap
+//! * `0, 2, dx, dy`: delta, skips `dx` pixels to the right and `dy` rows down
+//! * `0, n` with `n >= 3`: absolute mode, `n` pixels follow, padded to an even number of bytes
+//!
+//! Pixels that are skipped by an end of line, a delta or the end of bitmap are zero.
+//!
+//! # Example
+//! ```
+//! # use rle_vec::{RleVec, bmp};
+//! let rows = vec![RleVec::from(&[7u8, 7, 7, 1, 2, 3][..]), RleVec::from(&[4u8; 6][..])];
+//!
+//! let compressed = bmp::encode_rle8(&rows);
+//! assert_eq!(compressed, vec![3, 7, 0, 3, 1, 2, 3, 0, 0, 0, 6, 4, 0, 1]);
+//! assert_eq!(bmp::decode_rle8(&compressed, 6, 2).unwrap(), rows);
+//! ```
+
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+
+use super::{RleIndex, RleVec};
+
+// the longest run or absolute packet
+const MAX_PACKET: usize = 255;
+const END_OF_LINE: u8 = 0;
+const END_OF_BITMAP: u8 = 1;
+const DELTA: u8 = 2;
+
+/// The error returned when decoding a malformed RLE8 or RLE4 bitmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The packet at this offset is cut off by the end of the data.
+    Truncated {
+        /// The offset of the packet.
+        offset: usize,
+    },
+    /// The data ends without an end of bitmap escape.
+    MissingEndOfBitmap,
+    /// The packet at this offset writes pixels past the end of the row.
+    RowOverflow {
+        /// The offset of the packet.
+        offset: usize,
+        /// The row that overflows.
+        row: usize,
+    },
+    /// The packet at this offset writes pixels below the last row.
+    TooManyRows {
+        /// The offset of the packet.
+        offset: usize,
+    },
+    /// The delta at this offset moves outside the bitmap.
+    DeltaOutOfBounds {
+        /// The offset of the packet.
+        offset: usize,
+    },
+    /// Allocating the rows of the bitmap failed.
+    TooLarge {
+        /// The number of rows.
+        height: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated { offset } =>
+                write!(f, "the packet at offset {} is truncated", offset),
+            DecodeError::MissingEndOfBitmap =>
+                write!(f, "the bitmap has no end of bitmap escape"),
+            DecodeError::RowOverflow { offset, row } =>
+                write!(f, "the packet at offset {} overflows row {}", offset, row),
+            DecodeError::TooManyRows { offset } =>
+                write!(f, "the packet at offset {} is below the last row", offset),
+            DecodeError::DeltaOutOfBounds { offset } =>
+                write!(f, "the delta at offset {} moves outside the bitmap", offset),
+            DecodeError::TooLarge { height } =>
+                write!(f, "the {} rows of the bitmap can not be allocated", height),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Depth {
+    Rle4,
+    Rle8,
+}
+
+impl Depth {
+    // the byte of a run packet
+    fn run_byte(self, value: u8) -> u8 {
+        match self {
+            Depth::Rle4 => value << 4 | value,
+            Depth::Rle8 => value,
+        }
+    }
+
+    fn bytes(self, pixels: usize) -> usize {
+        match self {
+            Depth::Rle4 => pixels.div_ceil(2),
+            Depth::Rle8 => pixels,
+        }
+    }
+}
+
+/// Encodes the rows of a bitmap with 8 bit color indices as RLE8.
+///
+/// The rows may have different lengths, each one ends with an end of line escape except the
+/// last one, which ends with the end of bitmap.
+pub fn encode_rle8<I: RleIndex>(rows: &[RleVec<u8, I>]) -> Vec<u8> {
+    encode(rows, Depth::Rle8)
+}
+
+/// Encodes the rows of a bitmap with 4 bit color indices as RLE4.
+///
+/// # Panics
+/// Panics if a pixel is larger than 15.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, bmp};
+/// let rows = vec![RleVec::from(&[2u8, 2, 2, 2, 1, 2, 3][..])];
+/// assert_eq!(bmp::encode_rle4(&rows), vec![4, 0x22, 0, 3, 0x12, 0x30, 0, 1]);
+/// ```
+pub fn encode_rle4<I: RleIndex>(rows: &[RleVec<u8, I>]) -> Vec<u8> {
+    encode(rows, Depth::Rle4)
+}
+
+fn encode<I: RleIndex>(rows: &[RleVec<u8, I>], depth: Depth) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            out.extend_from_slice(&[0, END_OF_LINE]);
+        }
+        encode_row(row, depth, &mut out);
+    }
+    out.extend_from_slice(&[0, END_OF_BITMAP]);
+    out
+}
+
+fn encode_row<I: RleIndex>(row: &RleVec<u8, I>, depth: Depth, out: &mut Vec<u8>) {
+    let mut literal = Vec::new();
+    for run in row.runs() {
+        let value = *run.value;
+        if depth == Depth::Rle4 {
+            assert!(value < 16, "the RLE4 pixel {} is larger than 15", value);
+        }
+        if run.len == 1 {
+            literal.push(value);
+            if literal.len() == MAX_PACKET {
+                flush_literal(&mut literal, depth, out);
+            }
+            continue
+        }
+
+        flush_literal(&mut literal, depth, out);
+        let mut len = run.len;
+        while len > 0 {
+            let n = cmp::min(len, MAX_PACKET);
+            out.push(n as u8);
+            out.push(depth.run_byte(value));
+            len -= n;
+        }
+    }
+    flush_literal(&mut literal, depth, out);
+}
+
+fn flush_literal(literal: &mut Vec<u8>, depth: Depth, out: &mut Vec<u8>) {
+    // absolute mode needs at least three pixels
+    if literal.len() < 3 {
+        for &value in literal.iter() {
+            out.push(1);
+            out.push(depth.run_byte(value));
+        }
+    } else {
+        out.push(0);
+        out.push(literal.len() as u8);
+        match depth {
+            Depth::Rle4 => {
+                for pair in literal.chunks(2) {
+                    out.push(pair[0] << 4 | pair.get(1).cloned().unwrap_or(0));
+                }
+            }
+            Depth::Rle8 => out.extend_from_slice(literal),
+        }
+        if depth.bytes(literal.len()) % 2 == 1 {
+            out.push(0);
+        }
+    }
+    literal.clear();
+}
+
+/// Decodes an RLE8 bitmap of `height` rows of `width` pixels.
+///
+/// # Panics
+/// Panics if `width` overflows the index type.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, bmp};
+/// // three 4s, a delta to the next row and two 5s
+/// let data = [3, 4, 0, 2, 0, 1, 2, 5, 0, 1];
+/// let rows: Vec<RleVec<u8>> = bmp::decode_rle8(&data, 5, 2).unwrap();
+/// assert_eq!(rows[0].to_vec(), vec![4, 4, 4, 0, 0]);
+/// assert_eq!(rows[1].to_vec(), vec![0, 0, 0, 5, 5]);
+/// ```
+pub fn decode_rle8<I: RleIndex>(data: &[u8], width: usize, height: usize)
+    -> Result<Vec<RleVec<u8, I>>, DecodeError>
+{
+    decode(data, width, height, Depth::Rle8)
+}
+
+/// Decodes an RLE4 bitmap of `height` rows of `width` pixels.
+///
+/// # Panics
+/// Panics if `width` overflows the index type.
+pub fn decode_rle4<I: RleIndex>(data: &[u8], width: usize, height: usize)
+    -> Result<Vec<RleVec<u8, I>>, DecodeError>
+{
+    decode(data, width, height, Depth::Rle4)
+}
+
+/// The position of the next pixel while decoding.
+///
+/// The rows are only allocated when a packet reaches them, so a corrupt height does not
+/// allocate before the data is decoded.
+struct Canvas<I> {
+    rows: Vec<RleVec<u8, I>>,
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+}
+
+impl<I: RleIndex> Canvas<I> {
+    /// Returns the row to append `n` pixels to, filling the skipped pixels with zeros.
+    fn row(&mut self, n: usize, offset: usize) -> Result<&mut RleVec<u8, I>, DecodeError> {
+        if self.y >= self.height {
+            return Err(DecodeError::TooManyRows { offset })
+        }
+        if self.x + n > self.width {
+            return Err(DecodeError::RowOverflow { offset, row: self.y })
+        }
+        while self.rows.len() <= self.y {
+            self.rows.push(RleVec::default());
+        }
+        let row = &mut self.rows[self.y];
+        if row.len() < self.x {
+            let skipped = self.x - row.len();
+            row.push_n(skipped, 0);
+        }
+        self.x += n;
+        Ok(row)
+    }
+}
+
+fn decode<I: RleIndex>(data: &[u8], width: usize, height: usize, depth: Depth)
+    -> Result<Vec<RleVec<u8, I>>, DecodeError>
+{
+    let mut canvas = Canvas { rows: Vec::new(), width, height, x: 0, y: 0 };
+    let mut offset = 0;
+    loop {
+        let packet = offset;
+        let (first, second) = match data.get(offset..offset + 2) {
+            Some(header) => (header[0], header[1]),
+            None if offset == data.len() => return Err(DecodeError::MissingEndOfBitmap),
+            None => return Err(DecodeError::Truncated { offset }),
+        };
+        offset += 2;
+        match (first, second) {
+            (0, END_OF_LINE) => {
+                if canvas.y >= height {
+                    return Err(DecodeError::TooManyRows { offset: packet })
+                }
+                canvas.x = 0;
+                canvas.y += 1;
+            }
+            (0, END_OF_BITMAP) => break,
+            (0, DELTA) => {
+                let delta = data.get(offset..offset + 2).ok_or(DecodeError::Truncated { offset: packet })?;
+                let (x, y) = (canvas.x + delta[0] as usize, canvas.y + delta[1] as usize);
+                if x > width || y >= height {
+                    return Err(DecodeError::DeltaOutOfBounds { offset: packet })
+                }
+                canvas.x = x;
+                canvas.y = y;
+                offset += 2;
+            }
+            (0, n) => {
+                let n = n as usize;
+                let bytes = depth.bytes(n);
+                let padded = bytes + bytes % 2;
+                let pixels = data.get(offset..offset + padded).ok_or(DecodeError::Truncated { offset: packet })?;
+                let row = canvas.row(n, packet)?;
+                match depth {
+                    Depth::Rle4 => {
+                        for i in 0..n {
+                            let byte = pixels[i / 2];
+                            row.push(if i % 2 == 0 { byte >> 4 } else { byte & 0xf });
+                        }
+                    }
+                    Depth::Rle8 => {
+                        for &pixel in &pixels[..n] {
+                            row.push(pixel);
+                        }
+                    }
+                }
+                offset += padded;
+            }
+            (n, value) => {
+                let n = n as usize;
+                let row = canvas.row(n, packet)?;
+                let (high, low) = (value >> 4, value & 0xf);
+                if depth == Depth::Rle8 {
+                    row.push_n(n, value);
+                } else if high == low {
+                    row.push_n(n, high);
+                } else {
+                    for i in 0..n {
+                        row.push(if i % 2 == 0 { high } else { low });
+                    }
+                }
+            }
+        }
+    }
+
+    // the rows after the end of bitmap are all zeros
+    let mut rows = canvas.rows;
+    rows.try_reserve_exact(height - rows.len()).map_err(|_| DecodeError::TooLarge { height })?;
+    rows.resize_with(height, RleVec::default);
+    for row in &mut rows {
+        if row.len() < width {
+            let skipped = width - row.len();
+            row.push_n(skipped, 0);
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::Rng;
+
+    fn rows(rows: &[Vec<u8>]) -> Vec<RleVec<u8>> {
+        rows.iter().map(|row| RleVec::from(&row[..])).collect()
+    }
+
+    fn padded(pixels: &[u8], skip: usize, width: usize) -> Vec<u8> {
+        let mut row = vec![0; skip];
+        row.extend_from_slice(pixels);
+        row.resize(width, 0);
+        row
+    }
+
+    #[test]
+    fn known_vectors() {
+        // the examples from the BMP documentation
+        let rle8 = [
+            0x03, 0x04, 0x05, 0x06, 0x00, 0x03, 0x45, 0x56, 0x67, 0x00, 0x02, 0x78, 0x00, 0x02,
+            0x05, 0x01, 0x02, 0x78, 0x00, 0x00, 0x09, 0x1e, 0x00, 0x01,
+        ];
+        let expected = rows(&[
+            padded(&[4, 4, 4, 6, 6, 6, 6, 6, 0x45, 0x56, 0x67, 0x78, 0x78], 0, 20),
+            padded(&[0x78, 0x78], 18, 20),
+            padded(&[0x1e; 9], 0, 20),
+        ]);
+        assert_eq!(decode_rle8::<usize>(&rle8, 20, 3).unwrap(), expected);
+
+        let rle4 = [
+            0x03, 0x04, 0x05, 0x06, 0x00, 0x06, 0x45, 0x56, 0x67, 0x00, 0x04, 0x78, 0x00, 0x02,
+            0x05, 0x01, 0x04, 0x78, 0x00, 0x00, 0x09, 0x1e, 0x00, 0x01,
+        ];
+        let expected = rows(&[
+            padded(&[0, 4, 0, 0, 6, 0, 6, 0, 4, 5, 5, 6, 6, 7, 7, 8, 7, 8], 0, 28),
+            padded(&[7, 8, 7, 8], 23, 28),
+            padded(&[1, 0xe, 1, 0xe, 1, 0xe, 1, 0xe, 1], 0, 28),
+        ]);
+        assert_eq!(decode_rle4::<usize>(&rle4, 28, 3).unwrap(), expected);
+    }
+
+    #[test]
+    fn roundtrips() {
+        let mut rng = Rng::new(0x2545_f491);
+        for &width in &[1, 2, 3, 5, 64, 300, 600] {
+            let bitmap: Vec<Vec<u8>> = (0..6).map(|_| {
+                let mut row = Vec::new();
+                while row.len() < width {
+                    let len = cmp::min(rng.below(6) + 1, width - row.len());
+                    row.extend(std::iter::repeat_n(rng.below(16) as u8, len));
+                }
+                row
+            }).collect();
+            let bitmap = rows(&bitmap);
+
+            let rle8 = encode_rle8(&bitmap);
+            assert_eq!(decode_rle8::<usize>(&rle8, width, 6).unwrap(), bitmap);
+            let rle4 = encode_rle4(&bitmap);
+            assert_eq!(decode_rle4::<usize>(&rle4, width, 6).unwrap(), bitmap);
+        }
+    }
+
+    #[test]
+    fn malformed_bitmaps() {
+        let bitmap = rows(&[vec![1, 2, 3, 3, 3, 4], vec![5; 6]]);
+        let data = encode_rle8(&bitmap);
+        for end in 0..data.len() {
+            let error = decode_rle8::<usize>(&data[..end], 6, 2).unwrap_err();
+            if end % 2 == 0 {
+                assert_eq!(error, DecodeError::MissingEndOfBitmap);
+            } else {
+                assert!(matches!(error, DecodeError::Truncated { .. }));
+            }
+        }
+
+        assert_eq!(decode_rle8::<usize>(&data, 5, 2), Err(DecodeError::RowOverflow { offset: 6, row: 0 }));
+        assert_eq!(decode_rle8::<usize>(&data, 6, 1), Err(DecodeError::TooManyRows { offset: 10 }));
+        assert_eq!(decode_rle8::<usize>(&[0, 2, 1, 2, 0, 1], 6, 2),
+                   Err(DecodeError::DeltaOutOfBounds { offset: 0 }));
+        assert_eq!(decode_rle8::<usize>(&[0, 2, 7, 0, 0, 1], 6, 2),
+                   Err(DecodeError::DeltaOutOfBounds { offset: 0 }));
+        assert_eq!(decode_rle8::<usize>(&[0, 0, 0, 0, 0, 0, 0, 1], 6, 2),
+                   Err(DecodeError::TooManyRows { offset: 4 }));
+
+        // the rows of a corrupt height are not allocated before the data is decoded
+        assert_eq!(decode_rle8::<usize>(&[2, 7], 4, 1 << 31), Err(DecodeError::MissingEndOfBitmap));
+        assert_eq!(decode_rle8::<usize>(&[0, 1], 4, usize::MAX / 2),
+                   Err(DecodeError::TooLarge { height: usize::MAX / 2 }));
+        assert_eq!(decode_rle8::<usize>(&[2, 7, 0, 1], 2, 3).unwrap(),
+                   rows(&[vec![7, 7], vec![0, 0], vec![0, 0]]));
+    }
+}
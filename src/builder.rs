@@ -0,0 +1,335 @@
+//! Lossy construction of `RleVec`s from noisy floating point signals.
+
+use std::iter::FromIterator;
+
+use super::RleVec;
+
+/// Floating point types that can be quantized by the [`RleBuilder`](struct.RleBuilder.html).
+///
+/// All calculations are performed in `f64`, the representative value of a run is converted
+/// back to `Self` when the run is closed.
+pub trait Quantizable: Copy + PartialEq {
+    /// Widen the value to an `f64`.
+    fn to_f64(self) -> f64;
+    /// Convert a representative value back, rounding to the nearest representable value.
+    fn from_f64(value: f64) -> Self;
+}
+
+impl Quantizable for f32 {
+    fn to_f64(self) -> f64 { self as f64 }
+    fn from_f64(value: f64) -> Self { value as f32 }
+}
+
+impl Quantizable for f64 {
+    fn to_f64(self) -> f64 { self }
+    fn from_f64(value: f64) -> Self { value }
+}
+
+/// The maximum distance a value may have from the representative value of its run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+    /// Values may differ at most this amount from the representative.
+    Absolute(f64),
+    /// Values may differ at most this fraction of the absolute representative value.
+    Relative(f64),
+}
+
+impl Tolerance {
+    fn allowed(self, representative: f64) -> f64 {
+        match self {
+            Tolerance::Absolute(tolerance) => tolerance,
+            Tolerance::Relative(fraction) => fraction * representative.abs(),
+        }
+    }
+}
+
+/// The value that is stored for a run of merged values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Representative {
+    /// The first value of the run.
+    First,
+    /// The mean of all values in the run.
+    Mean,
+    /// The middle between the smallest and the largest value in the run.
+    Midrange,
+}
+
+/// Statistics of the run that is currently being built.
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    len: usize,
+    first: f64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Pending {
+    fn new(n: usize, value: f64) -> Pending {
+        Pending { len: n, first: value, sum: value * n as f64, min: value, max: value }
+    }
+
+    fn representative(&self, representative: Representative) -> f64 {
+        match representative {
+            Representative::First => self.first,
+            Representative::Mean => self.sum / self.len as f64,
+            Representative::Midrange => (self.min + self.max) / 2.0,
+        }
+    }
+
+    fn with(&self, n: usize, value: f64) -> Pending {
+        Pending {
+            len: self.len + n,
+            first: self.first,
+            sum: self.sum + value * n as f64,
+            min: self.min.min(value),
+            max: self.max.max(value),
+        }
+    }
+}
+
+/// Builds an `RleVec` from floating point values, merging values that are within a tolerance
+/// of each other into a single run.
+///
+/// Sampled signals rarely contain identical consecutive values, so building an `RleVec` with
+/// exact comparisons stores one run per sample. The `RleBuilder` adds a value to the current run
+/// as long as every value of the run stays within the [`Tolerance`](enum.Tolerance.html) of the
+/// run's [`Representative`](enum.Representative.html). The representative is the value that is
+/// stored in the resulting `RleVec`, the largest difference between an original value and its
+/// stored representative is available from [`max_error`](#method.max_error).
+///
+/// A tolerance of `Tolerance::Absolute(0.0)` only merges equal values.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleBuilder, Tolerance, Representative, Run};
+/// let mut builder = RleBuilder::new(Tolerance::Absolute(0.5), Representative::Mean);
+/// builder.extend(vec![1.0f32, 1.2, 0.8, 5.0, 5.5, 5.25]);
+///
+/// assert_eq!(builder.max_error(), 0.25);
+///
+/// let rle = builder.finish();
+/// assert_eq!(rle.len(), 6);
+/// assert_eq!(rle.runs().collect::<Vec<_>>(), vec![Run { len: 3, value: &1.0 },
+///                                                 Run { len: 3, value: &5.25 }]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RleBuilder<T> {
+    rle: RleVec<T>,
+    tolerance: Tolerance,
+    representative: Representative,
+    pending: Option<Pending>,
+    max_error: f64,
+}
+
+impl<T: Quantizable> RleBuilder<T> {
+    /// Constructs a new empty builder using the given tolerance and representative.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleBuilder, Tolerance, Representative};
+    /// let mut builder = RleBuilder::new(Tolerance::Relative(0.01), Representative::First);
+    /// builder.push(100.0f64);
+    /// builder.push(100.9);
+    /// builder.push(101.1);
+    ///
+    /// let rle = builder.finish();
+    /// assert_eq!(rle.to_vec(), vec![100.0, 100.0, 101.1]);
+    /// ```
+    pub fn new(tolerance: Tolerance, representative: Representative) -> RleBuilder<T> {
+        RleBuilder {
+            rle: RleVec::new(),
+            tolerance,
+            representative,
+            pending: None,
+            max_error: 0.0,
+        }
+    }
+
+    /// Returns the number of values pushed into the builder.
+    pub fn len(&self) -> usize {
+        self.rle.len() + self.pending.map_or(0, |p| p.len)
+    }
+
+    /// Returns `true` if no values have been pushed into the builder.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the largest absolute difference between a value pushed so far and the value that
+    /// will be stored for it.
+    pub fn max_error(&self) -> f64 {
+        match self.pending {
+            Some(pending) => self.max_error.max(self.error(&pending)),
+            None => self.max_error,
+        }
+    }
+
+    /// Appends a value, merging it with the current run if the run stays within tolerance.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.push_n(1, value);
+    }
+
+    /// Appends the same value n times.
+    ///
+    /// # Panics
+    /// Panics if the number of elements overflows a usize.
+    pub fn push_n(&mut self, n: usize, value: T) {
+        if n == 0 { return }
+        let value = value.to_f64();
+
+        let pending = match self.pending {
+            Some(pending) => {
+                let merged = pending.with(n, value);
+                if self.within_tolerance(&merged) {
+                    merged
+                } else {
+                    self.close(pending);
+                    Pending::new(n, value)
+                }
+            }
+            None => Pending::new(n, value),
+        };
+        self.pending = Some(pending);
+    }
+
+    /// Closes the current run and returns the constructed `RleVec`.
+    pub fn finish(mut self) -> RleVec<T> {
+        if let Some(pending) = self.pending.take() {
+            self.close(pending);
+        }
+        self.rle
+    }
+
+    fn within_tolerance(&self, pending: &Pending) -> bool {
+        let representative = pending.representative(self.representative);
+        let allowed = self.tolerance.allowed(representative);
+        // min and max skip NaN values, the sum does not. NaN values always get their own run.
+        !pending.sum.is_nan()
+            && representative - pending.min <= allowed
+            && pending.max - representative <= allowed
+    }
+
+    fn error(&self, pending: &Pending) -> f64 {
+        let stored = T::from_f64(pending.representative(self.representative)).to_f64();
+        (stored - pending.min).abs().max((pending.max - stored).abs())
+    }
+
+    fn close(&mut self, pending: Pending) {
+        self.max_error = self.max_error.max(self.error(&pending));
+        let value = T::from_f64(pending.representative(self.representative));
+
+        // representatives of neighbouring runs can end up equal after rounding
+        let end = self.rle.len() + pending.len - 1;
+        match self.rle.values.last() {
+            Some(last) if *last == value => *self.rle.ends.last_mut().unwrap() = end,
+            _ => {
+                self.rle.ends.push(end);
+                self.rle.values.push(value);
+            }
+        }
+    }
+}
+
+impl<T: Quantizable> Extend<T> for RleBuilder<T> {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=T> {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T: Quantizable> From<RleBuilder<T>> for RleVec<T> {
+    fn from(builder: RleBuilder<T>) -> RleVec<T> {
+        builder.finish()
+    }
+}
+
+impl<T: Quantizable> FromIterator<T> for RleBuilder<T> {
+    /// Collects the values into an exact builder, equal to a tolerance of
+    /// `Tolerance::Absolute(0.0)`.
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=T> {
+        let mut builder = RleBuilder::new(Tolerance::Absolute(0.0), Representative::First);
+        builder.extend(iter);
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Run;
+
+    #[test]
+    fn exact() {
+        let builder: RleBuilder<f32> = vec![1.0, 1.0, 2.0, 2.0, 2.0, 1.0].into_iter().collect();
+        assert_eq!(builder.max_error(), 0.0);
+        let rle = builder.finish();
+        assert_eq!(rle.to_vec(), vec![1.0, 1.0, 2.0, 2.0, 2.0, 1.0]);
+        assert_eq!(rle.runs_len(), 3);
+
+        let rle = RleBuilder::<f64>::new(Tolerance::Absolute(1.0), Representative::Mean).finish();
+        assert!(rle.is_empty());
+    }
+
+    #[test]
+    fn representatives() {
+        let values = [0.0f64, 0.4, 0.8, 1.2, 1.6];
+
+        // the first value is fixed, values drift away until out of tolerance
+        let mut builder = RleBuilder::new(Tolerance::Absolute(0.5), Representative::First);
+        builder.extend(values.iter().cloned());
+        assert_eq!(builder.len(), 5);
+        let rle = builder.finish();
+        assert_eq!(rle.runs().collect::<Vec<_>>(), vec![Run { len: 2, value: &0.0 },
+                                                        Run { len: 2, value: &0.8 },
+                                                        Run { len: 1, value: &1.6 }]);
+
+        // the running mean follows the values but all of them have to stay within tolerance
+        let mut builder = RleBuilder::new(Tolerance::Absolute(0.5), Representative::Mean);
+        builder.extend(values.iter().cloned());
+        assert!(builder.max_error() <= 0.5);
+        let rle = builder.finish();
+        assert_eq!(rle.runs_len(), 2);
+        assert_eq!(rle.runs().map(|r| r.len).collect::<Vec<_>>(), vec![3, 2]);
+
+        // the midrange allows a spread of twice the tolerance
+        let mut builder = RleBuilder::new(Tolerance::Absolute(0.5), Representative::Midrange);
+        builder.extend(values.iter().cloned());
+        assert!((builder.max_error() - 0.4).abs() < 1e-12);
+        let rle = builder.finish();
+        assert_eq!(rle.runs().map(|r| r.len).collect::<Vec<_>>(), vec![3, 2]);
+        assert!((rle[0] - 0.4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn relative_tolerance() {
+        let mut builder = RleBuilder::new(Tolerance::Relative(0.1), Representative::First);
+        builder.extend(vec![10.0f32, 10.9, 11.1, 100.0, 109.0, -5.0, -5.4]);
+        let rle = builder.finish();
+        assert_eq!(rle.to_vec(), vec![10.0, 10.0, 11.1, 100.0, 100.0, -5.0, -5.0]);
+    }
+
+    #[test]
+    fn merges_equal_representatives() {
+        // -0.9 cannot join the first run, the mean would drop to -0.3 and leave 1.0 at a distance
+        // of 1.3, but the second run ends up with the same mean of 0.0
+        let mut builder = RleBuilder::new(Tolerance::Absolute(1.0), Representative::Mean);
+        builder.extend(vec![-1.0f32, 1.0, -0.9, 0.9]);
+        assert_eq!(builder.max_error(), 1.0);
+        let rle = builder.finish();
+        assert_eq!(rle.runs().collect::<Vec<_>>(), vec![Run { len: 4, value: &0.0 }]);
+
+        let mut builder = RleBuilder::new(Tolerance::Absolute(0.0), Representative::Mean);
+        builder.push_n(0, 3.0f32);
+        builder.push(f32::NAN);
+        builder.push(f32::NAN);
+        builder.push(1.0);
+        assert_eq!(builder.len(), 3);
+        let rle = builder.finish();
+        assert_eq!(rle.runs_len(), 3);
+        assert!(rle[1].is_nan());
+        assert_eq!(rle[2], 1.0);
+    }
+}
@@ -0,0 +1,567 @@
+//! A run-length encoded vector that stores its runs in blocks with a start offset.
+
+use std::cmp;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::slice;
+
+use super::{RleIndex, RleVec, Run};
+
+// blocks are never split below this number of runs
+const MIN_BLOCK_RUNS: usize = 32;
+
+/// A vector of runs split into blocks, each block storing run ends relative to its own start.
+///
+/// Inserting or removing an element of an [`RleVec`](../struct.RleVec.html) shifts the end of
+/// every following run. `ChunkedRleVec` groups the runs into blocks of about √n runs and keeps
+/// the start position of every block, so an insert or remove only shifts the ends within one
+/// block and the starts of the following blocks, which is **O(√n)** instead of **O(n)**. Finding
+/// an index is a binary search over the block starts followed by one within the block.
+///
+/// Runs are merged across block boundaries, so `runs` returns the same runs as an `RleVec`
+/// holding the same values.
+///
+/// # Example
+/// ```
+/// # use rle_vec::ChunkedRleVec;
+/// let mut rle = ChunkedRleVec::from(&[0, 0, 0, 1, 1, 2][..]);
+///
+/// rle.insert(1, 3);
+/// rle.set(5, 0);
+/// assert_eq!(rle.remove(0), 0);
+///
+/// assert_eq!(rle.to_vec(), vec![3, 0, 0, 1, 0, 2]);
+/// assert_eq!(rle.runs_len(), 5);
+/// ```
+#[derive(Clone)]
+pub struct ChunkedRleVec<T> {
+    blocks: Vec<RleVec<T>>,
+    // the position of the first element of every block
+    starts: Vec<usize>,
+    len: usize,
+    runs: usize,
+}
+
+impl<T> ChunkedRleVec<T> {
+    /// Constructs a new empty `ChunkedRleVec<T>`.
+    pub fn new() -> ChunkedRleVec<T> {
+        ChunkedRleVec { blocks: Vec::new(), starts: Vec::new(), len: 0, runs: 0 }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Clears the vector, removing all values.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.starts.clear();
+        self.len = 0;
+        self.runs = 0;
+    }
+
+    /// Returns the number of runs.
+    pub fn runs_len(&self) -> usize {
+        self.runs
+    }
+
+    /// Returns the last value, or None if it is empty.
+    pub fn last(&self) -> Option<&T> {
+        self.blocks.last().and_then(|block| block.last())
+    }
+
+    /// Returns the last run, or None if it is empty.
+    pub fn last_run(&self) -> Option<Run<&T>> {
+        self.blocks.last().and_then(|block| block.last_run())
+    }
+
+    /// Returns the 0-based start coordinates of the runs
+    pub fn starts(&self) -> Vec<usize> {
+        self.blocks.iter().zip(&self.starts)
+            .flat_map(|(block, &start)| block.starts().into_iter().map(move |s| s + start))
+            .collect()
+    }
+
+    /// Returns the 0-based end coordinates of the runs
+    pub fn ends(&self) -> Vec<usize> {
+        self.blocks.iter().zip(&self.starts)
+            .flat_map(|(block, &start)| block.ends().iter().map(move |end| end + start))
+            .collect()
+    }
+
+    /// Returns an iterator over values.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { blocks: self.blocks.iter(), inner: None, remaining: self.len }
+    }
+
+    /// Returns an iterator over the runs.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{ChunkedRleVec, Run};
+    /// let rle = ChunkedRleVec::from(&[1, 1, 2][..]);
+    ///
+    /// let mut iterator = rle.runs();
+    /// assert_eq!(iterator.next(), Some(Run{ len: 2, value: &1 }));
+    /// assert_eq!(iterator.next(), Some(Run{ len: 1, value: &2 }));
+    /// assert_eq!(iterator.next(), None);
+    /// ```
+    pub fn runs(&self) -> Runs<'_, T> {
+        Runs { blocks: self.blocks.iter(), inner: None, remaining: self.runs }
+    }
+
+    fn block_index(&self, index: usize) -> usize {
+        if index >= self.len {
+            panic!("index out of bounds: the len is {} but the index is {}", self.len, index)
+        }
+        self.starts.partition_point(|&start| start <= index) - 1
+    }
+
+    fn max_block_runs(&self) -> usize {
+        cmp::max(MIN_BLOCK_RUNS, 2 * (self.runs as f64).sqrt() as usize)
+    }
+
+    fn remove_block(&mut self, b: usize) {
+        self.blocks.remove(b);
+        self.starts.remove(b);
+    }
+
+    /// Splits a block in two if it grew too large.
+    fn split_block(&mut self, b: usize) -> bool {
+        let runs = self.blocks[b].runs_len();
+        if runs <= self.max_block_runs() {
+            return false
+        }
+
+        let block = &mut self.blocks[b];
+        let at = runs / 2;
+        let offset = block.ends[at - 1] + 1;
+        let ends = block.ends.split_off(at).into_iter().map(|end| end - offset).collect();
+        let values = block.values.split_off(at);
+        let start = self.starts[b] + offset;
+
+        self.blocks.insert(b + 1, RleVec { ends, values });
+        self.starts.insert(b + 1, start);
+        true
+    }
+
+    /// Joins a small block with its successor.
+    fn join_small_block(&mut self, b: usize) {
+        if b + 1 >= self.blocks.len() { return }
+        let max = self.max_block_runs();
+        let runs = self.blocks[b].runs_len() + self.blocks[b + 1].runs_len();
+        if runs * 2 > max { return }
+
+        let next = self.blocks.remove(b + 1);
+        self.starts.remove(b + 1);
+        let block = &mut self.blocks[b];
+        let offset = block.len();
+        block.ends.extend(next.ends.into_iter().map(|end| end + offset));
+        block.values.extend(next.values);
+    }
+
+    fn shift_starts(&mut self, from_block: usize, grow: bool) {
+        for start in self.starts[from_block..].iter_mut() {
+            if grow { *start += 1 } else { *start -= 1 }
+        }
+    }
+}
+
+impl<T: PartialEq> ChunkedRleVec<T> {
+    /// Moves the first run of block `b + 1` to block `b` if their values are equal.
+    fn join_seam(&mut self, b: usize) {
+        if b + 1 >= self.blocks.len() || self.blocks[b].last() != Some(&self.blocks[b + 1].values[0]) {
+            return
+        }
+
+        let next = &mut self.blocks[b + 1];
+        let first_len = next.ends.remove(0) + 1;
+        next.values.remove(0);
+        for end in next.ends.iter_mut() {
+            *end -= first_len;
+        }
+        let next_empty = next.is_empty();
+
+        *self.blocks[b].ends.last_mut().unwrap() += first_len;
+        self.starts[b + 1] += first_len;
+        self.runs -= 1;
+        if next_empty {
+            self.remove_block(b + 1);
+        }
+    }
+
+    /// Restores merged runs around block `b` after it was modified.
+    fn fix_seams(&mut self, b: usize) {
+        self.join_seam(b);
+        if b > 0 {
+            self.join_seam(b - 1);
+        }
+    }
+}
+
+impl<T: Eq> ChunkedRleVec<T> {
+    /// Appends an element to the back of the vector.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.push_n(1, value);
+    }
+
+    /// Appends the same element n times to the back of the vector.
+    ///
+    /// # Panics
+    /// Panics if the number of elements overflows a usize.
+    pub fn push_n(&mut self, n: usize, value: T) {
+        if n == 0 { return }
+        if self.blocks.is_empty() {
+            self.blocks.push(RleVec::new());
+            self.starts.push(0);
+        }
+
+        let b = self.blocks.len() - 1;
+        let before = self.blocks[b].runs_len();
+        self.blocks[b].push_n(n, value);
+        self.runs += self.blocks[b].runs_len() - before;
+        self.len += n;
+        self.split_block(b);
+    }
+}
+
+impl<T: Clone> ChunkedRleVec<T> {
+    /// Construct a `Vec<T>` from this `ChunkedRleVec`.
+    pub fn to_vec(&self) -> Vec<T> {
+        let mut res = Vec::with_capacity(self.len);
+        for block in &self.blocks {
+            res.extend(block.iter().cloned());
+        }
+        res
+    }
+}
+
+impl<T: Eq + Clone> ChunkedRleVec<T> {
+    /// Modify the value at given index.
+    ///
+    /// # Panics
+    /// Panics if index is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) {
+        let b = self.block_index(index);
+        let before = self.blocks[b].runs_len();
+        self.blocks[b].set(index - self.starts[b], value);
+        self.runs = self.runs + self.blocks[b].runs_len() - before;
+
+        let last = if self.split_block(b) { b + 1 } else { b };
+        self.join_seam(last);
+        if b > 0 {
+            self.join_seam(b - 1);
+        }
+    }
+
+    /// Removes and returns the element at position index, shifting all elements after it to the left.
+    ///
+    /// Only the ends of the runs in a single block and the starts of the following blocks are
+    /// shifted, the complexity is **O(√n)**.
+    ///
+    /// # Panics
+    /// Panics if index is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        let b = self.block_index(index);
+        let before = self.blocks[b].runs_len();
+        let value = self.blocks[b].remove(index - self.starts[b]);
+        self.runs = self.runs + self.blocks[b].runs_len() - before;
+        self.len -= 1;
+        self.shift_starts(b + 1, false);
+
+        if self.blocks[b].is_empty() {
+            self.remove_block(b);
+            if b > 0 {
+                self.join_seam(b - 1);
+            }
+        } else {
+            self.fix_seams(b);
+            if b < self.blocks.len() {
+                self.join_small_block(b);
+            }
+        }
+        value
+    }
+
+    /// Insert a value at the given index.
+    ///
+    /// Only the ends of the runs in a single block and the starts of the following blocks are
+    /// shifted, the complexity is **O(√n)**.
+    ///
+    /// # Panics
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len, "insertion index (is {}) should be <= len (is {})", index, self.len);
+        if index == self.len {
+            return self.push(value);
+        }
+
+        let b = self.block_index(index);
+        let before = self.blocks[b].runs_len();
+        self.blocks[b].insert(index - self.starts[b], value);
+        self.runs = self.runs + self.blocks[b].runs_len() - before;
+        self.len += 1;
+        self.shift_starts(b + 1, true);
+
+        let last = if self.split_block(b) { b + 1 } else { b };
+        self.join_seam(last);
+        if b > 0 {
+            self.join_seam(b - 1);
+        }
+    }
+}
+
+impl<T> Index<usize> for ChunkedRleVec<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let b = self.block_index(index);
+        &self.blocks[b][index - self.starts[b]]
+    }
+}
+
+impl<T> Default for ChunkedRleVec<T> {
+    fn default() -> Self {
+        ChunkedRleVec::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ChunkedRleVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChunkedRleVec").field("runs", &self.runs().collect::<Vec<_>>()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for ChunkedRleVec<T> {
+    fn eq(&self, other: &ChunkedRleVec<T>) -> bool {
+        // the block boundaries depend on the order of the modifications
+        self.len == other.len && self.runs().eq(other.runs())
+    }
+}
+
+impl<T: Eq> Eq for ChunkedRleVec<T> {}
+
+impl<T: Clone> From<ChunkedRleVec<T>> for Vec<T> {
+    fn from(rle: ChunkedRleVec<T>) -> Vec<T> {
+        rle.to_vec()
+    }
+}
+
+impl<'a, T: Eq + Clone> From<&'a [T]> for ChunkedRleVec<T> {
+    fn from(slice: &'a [T]) -> Self {
+        slice.iter().cloned().collect()
+    }
+}
+
+impl<T: Eq + Clone, I: RleIndex> From<RleVec<T, I>> for ChunkedRleVec<T> {
+    fn from(rle: RleVec<T, I>) -> Self {
+        rle.runs().map(|run| Run { len: run.len, value: run.value.clone() }).collect()
+    }
+}
+
+impl<T: Eq + Clone> From<ChunkedRleVec<T>> for RleVec<T> {
+    fn from(rle: ChunkedRleVec<T>) -> Self {
+        rle.runs().map(|run| Run { len: run.len, value: run.value.clone() }).collect()
+    }
+}
+
+impl<T: Eq> FromIterator<T> for ChunkedRleVec<T> {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=T> {
+        let mut rle = ChunkedRleVec::new();
+        rle.extend(iter);
+        rle
+    }
+}
+
+impl<T: Eq> FromIterator<Run<T>> for ChunkedRleVec<T> {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=Run<T>> {
+        let mut rle = ChunkedRleVec::new();
+        rle.extend(iter);
+        rle
+    }
+}
+
+impl<T: Eq> Extend<T> for ChunkedRleVec<T> {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=T> {
+        let mut current: Option<Run<T>> = None;
+        for value in iter {
+            match current {
+                Some(ref mut run) if run.value == value => run.len += 1,
+                _ => {
+                    if let Some(Run { len, value }) = current.take() {
+                        self.push_n(len, value);
+                    }
+                    current = Some(Run { len: 1, value });
+                }
+            }
+        }
+        if let Some(Run { len, value }) = current {
+            self.push_n(len, value);
+        }
+    }
+}
+
+impl<T: Eq> Extend<Run<T>> for ChunkedRleVec<T> {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=Run<T>> {
+        for Run { len, value } in iter {
+            self.push_n(len, value);
+        }
+    }
+}
+
+impl<'a, T: 'a> IntoIterator for &'a ChunkedRleVec<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Immutable `ChunkedRleVec` iterator over references of values.
+///
+/// Can be obtained from the [`iter`](struct.ChunkedRleVec.html#method.iter) method.
+pub struct Iter<'a, T: 'a> {
+    blocks: slice::Iter<'a, RleVec<T>>,
+    inner: Option<super::Iter<'a, T>>,
+    remaining: usize,
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.inner.as_mut().and_then(|inner| inner.next()) {
+                self.remaining -= 1;
+                return Some(value)
+            }
+            self.inner = Some(self.blocks.next()?.iter());
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for Iter<'a, T> { }
+
+/// Immutable `ChunkedRleVec` iterator over runs.
+///
+/// Can be obtained from the [`runs`](struct.ChunkedRleVec.html#method.runs) method.
+pub struct Runs<'a, T: 'a> {
+    blocks: slice::Iter<'a, RleVec<T>>,
+    inner: Option<super::Runs<'a, T>>,
+    remaining: usize,
+}
+
+impl<'a, T: 'a> Iterator for Runs<'a, T> {
+    type Item = Run<&'a T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(run) = self.inner.as_mut().and_then(|inner| inner.next()) {
+                self.remaining -= 1;
+                return Some(run)
+            }
+            self.inner = Some(self.blocks.next()?.runs());
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for Runs<'a, T> { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::{same_as, Rng};
+
+    fn assert_consistent<T: Eq + Clone + fmt::Debug>(rle: &ChunkedRleVec<T>) {
+        let runs: Vec<_> = rle.runs().collect();
+        assert_eq!(runs.len(), rle.runs_len());
+        assert!(runs.windows(2).all(|w| w[0].value != w[1].value), "{:?}", runs);
+        assert!(rle.blocks.iter().all(|block| !block.is_empty()));
+        let mut start = 0;
+        for (block, &block_start) in rle.blocks.iter().zip(&rle.starts) {
+            assert_eq!(block_start, start);
+            start += block.len();
+        }
+        assert_eq!(start, rle.len());
+    }
+
+    #[test]
+    fn same_as_vec() {
+        let mut rng = Rng::new(7);
+
+        let mut v: Vec<u8> = (0..2_000).map(|_| rng.below(2) as u8).collect();
+        let mut rle = ChunkedRleVec::from(&v[..]);
+        assert!(rle.blocks.len() > 1);
+        assert_consistent(&rle);
+
+        same_as(8, 5_000, &mut v, &mut rle, |rng, v, rle| {
+            let value = rng.below(3) as u8;
+            match rng.below(4) {
+                0 => {
+                    let index = rng.below(v.len() + 1);
+                    v.insert(index, value);
+                    rle.insert(index, value);
+                }
+                1 if !v.is_empty() => {
+                    let index = rng.below(v.len());
+                    assert_eq!(rle.remove(index), v.remove(index));
+                }
+                2 if !v.is_empty() => {
+                    let index = rng.below(v.len());
+                    v[index] = value;
+                    rle.set(index, value);
+                }
+                _ => {
+                    v.push(value);
+                    rle.push(value);
+                }
+            }
+        }, |_, rle| assert_consistent(rle));
+        assert_eq!(rle.to_vec(), v);
+        assert_eq!(rle.iter().cloned().collect::<Vec<_>>(), v);
+        assert_eq!((0..v.len()).map(|i| rle[i]).collect::<Vec<_>>(), v);
+
+        let reference = RleVec::from(&v[..]);
+        assert_eq!(rle.starts(), reference.starts());
+        assert_eq!(rle.ends(), reference.ends());
+        assert_eq!(RleVec::from(rle), reference);
+    }
+
+    #[test]
+    fn merges_across_blocks() {
+        let mut rle: ChunkedRleVec<_> = (0..1_000).collect();
+        assert!(rle.blocks.len() > 10);
+
+        // remove everything from the middle, the outer blocks end up adjacent
+        while rle.len() > 2 {
+            rle.remove(1);
+            assert_consistent(&rle);
+        }
+        assert_eq!(rle.to_vec(), vec![0, 999]);
+
+        rle.set(1, 0);
+        assert_eq!(rle.runs_len(), 1);
+        rle.remove(0);
+        rle.remove(0);
+        assert!(rle.is_empty());
+        assert_eq!(rle.runs().next(), None);
+        assert_eq!(rle.iter().next(), None);
+    }
+}
@@ -0,0 +1,408 @@
+//! The run-length encoding of segmentation masks in the COCO dataset.
+//!
+//! A mask of `height` × `width` pixels is flattened in column-major order, and its counts are
+//! the lengths of the alternating runs of background and foreground pixels, starting with the
+//! background. The first count is zero for a mask starting with the foreground.
+//!
+//! The compressed string stores every count, from the third on as the difference to the count
+//! two before it, in groups of five bits from the least significant. A group is written as the
+//! character `48 + group`, with `0x20` added if another group follows, the last group holds
+//! the sign in its bit `0x10`.
+//!
+//! # Example
+//! ```
+//! # use rle_vec::RleVec;
+//! # use rle_vec::coco::CocoRle;
+//! // a 3 × 2 mask, the column-major pixels are 0 0 1, 1 1 0
+//! let mask: RleVec<bool> = [false, false, true, true, true, false].iter().cloned().collect();
+//!
+//! let rle = CocoRle::from_mask(&mask, 3, 2).unwrap();
+//! assert_eq!(rle.counts(), &[2, 3, 1]);
+//! assert_eq!(rle.to_compressed(), "231");
+//! assert_eq!(CocoRle::from_compressed(3, 2, "231").unwrap(), rle);
+//! assert_eq!(rle.area(), 3);
+//! assert_eq!(rle.bbox(), Some([0, 0, 2, 3]));
+//! ```
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use super::{RleIndex, RleVec, Run};
+
+/// The error returned when a COCO mask can not be constructed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CocoError {
+    /// The mask or the counts hold another number of pixels than `height * width`.
+    SizeMismatch {
+        /// The number of pixels, `height * width`.
+        expected: usize,
+        /// The length of the mask or the sum of the counts.
+        found: usize,
+    },
+    /// The number of pixels does not fit in a `u32` count.
+    CountOverflow,
+    /// The compressed string holds a character outside of `0` to `o` at this offset.
+    InvalidCharacter {
+        /// The byte offset of the character.
+        offset: usize,
+    },
+    /// The count at this offset is cut off by the end of the string.
+    Truncated {
+        /// The byte offset of the count.
+        offset: usize,
+    },
+    /// The count at this offset is negative or too large.
+    InvalidCount {
+        /// The byte offset of the count.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for CocoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CocoError::SizeMismatch { expected, found } =>
+                write!(f, "found {} pixels instead of {}", found, expected),
+            CocoError::CountOverflow => write!(f, "the number of pixels overflows the counts"),
+            CocoError::InvalidCharacter { offset } =>
+                write!(f, "invalid character at offset {}", offset),
+            CocoError::Truncated { offset } =>
+                write!(f, "the count at offset {} is truncated", offset),
+            CocoError::InvalidCount { offset } =>
+                write!(f, "the count at offset {} is out of range", offset),
+        }
+    }
+}
+
+impl Error for CocoError {}
+
+/// A segmentation mask in the run-length encoding of the COCO dataset.
+///
+/// The counts are kept canonical, only the first count can be zero, so two masks holding the
+/// same pixels compare equal. The area, bounding box, IoU, union and intersection are computed
+/// on the counts without decoding the pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CocoRle {
+    height: usize,
+    width: usize,
+    counts: Vec<u32>,
+}
+
+pub(crate) fn pixels(height: usize, width: usize) -> Result<usize, CocoError> {
+    height.checked_mul(width)
+        .filter(|&pixels| pixels <= u32::MAX as usize)
+        .ok_or(CocoError::CountOverflow)
+}
+
+// appends a run, the size check keeps the counts from overflowing
+fn push_count(counts: &mut Vec<u32>, value: bool, len: u32) {
+    if len == 0 {
+        return
+    }
+    let last = (counts.len() - 1) % 2 == 1;
+    if value == last {
+        *counts.last_mut().unwrap() += len;
+    } else {
+        counts.push(len);
+    }
+}
+
+// the non-empty runs of the counts
+fn runs(counts: &[u32]) -> impl Iterator<Item = (bool, u32)> + '_ {
+    counts.iter().enumerate()
+        .map(|(i, &count)| (i % 2 == 1, count))
+        .filter(|&(_, count)| count > 0)
+}
+
+impl CocoRle {
+    fn with_counts(height: usize, width: usize, runs: impl Iterator<Item = (bool, u32)>) -> CocoRle {
+        let mut counts = vec![0];
+        for (value, len) in runs {
+            push_count(&mut counts, value, len);
+        }
+        CocoRle { height, width, counts }
+    }
+
+    /// Encodes a mask of `height` × `width` pixels in column-major order.
+    ///
+    /// Returns an error if the length of the mask differs from `height * width`.
+    pub fn from_mask<I: RleIndex>(mask: &RleVec<bool, I>, height: usize, width: usize)
+        -> Result<CocoRle, CocoError>
+    {
+        let expected = pixels(height, width)?;
+        if mask.len() != expected {
+            return Err(CocoError::SizeMismatch { expected, found: mask.len() })
+        }
+        let runs = mask.runs().map(|Run { len, value }| (*value, len as u32));
+        Ok(CocoRle::with_counts(height, width, runs))
+    }
+
+    /// Constructs a mask of `height` × `width` pixels from its counts.
+    ///
+    /// Returns an error if the counts do not sum up to `height * width`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::coco::CocoRle;
+    /// // empty runs are merged into their neighbours
+    /// let rle = CocoRle::from_counts(2, 2, &[1, 0, 2, 1]).unwrap();
+    /// assert_eq!(rle.counts(), &[3, 1]);
+    /// ```
+    pub fn from_counts(height: usize, width: usize, counts: &[u32]) -> Result<CocoRle, CocoError> {
+        let expected = pixels(height, width)?;
+        let found = counts.iter().map(|&count| count as u64).sum::<u64>();
+        if found != expected as u64 {
+            let found = usize::try_from(found).unwrap_or(usize::MAX);
+            return Err(CocoError::SizeMismatch { expected, found })
+        }
+        Ok(CocoRle::with_counts(height, width, runs(counts)))
+    }
+
+    /// Decodes the compressed string of a mask of `height` × `width` pixels.
+    ///
+    /// Returns an error for a malformed string or if the counts do not sum up to
+    /// `height * width`.
+    pub fn from_compressed(height: usize, width: usize, s: &str) -> Result<CocoRle, CocoError> {
+        let bytes = s.as_bytes();
+        let mut counts: Vec<u32> = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let start = offset;
+            let (mut x, mut shift, mut more) = (0i64, 0, true);
+            while more {
+                let c = match bytes.get(offset) {
+                    Some(&c) if (48..48 + 64).contains(&c) => i64::from(c - 48),
+                    Some(_) => return Err(CocoError::InvalidCharacter { offset }),
+                    None => return Err(CocoError::Truncated { offset: start }),
+                };
+                if shift > 35 {
+                    return Err(CocoError::InvalidCount { offset: start })
+                }
+                x |= (c & 0x1f) << shift;
+                more = c & 0x20 != 0;
+                offset += 1;
+                shift += 5;
+                if !more && c & 0x10 != 0 {
+                    x |= -1 << shift;
+                }
+            }
+            if counts.len() > 2 {
+                x += i64::from(counts[counts.len() - 2]);
+            }
+            let count = u32::try_from(x).map_err(|_| CocoError::InvalidCount { offset: start })?;
+            counts.push(count);
+        }
+        CocoRle::from_counts(height, width, &counts)
+    }
+
+    /// Returns the compressed string of the counts.
+    pub fn to_compressed(&self) -> String {
+        let mut s = String::new();
+        for (i, &count) in self.counts.iter().enumerate() {
+            let mut x = i64::from(count);
+            if i > 2 {
+                x -= i64::from(self.counts[i - 2]);
+            }
+            loop {
+                let mut c = (x & 0x1f) as u8;
+                x >>= 5;
+                let more = if c & 0x10 != 0 { x != -1 } else { x != 0 };
+                if more {
+                    c |= 0x20;
+                }
+                s.push(char::from(c + 48));
+                if !more {
+                    break
+                }
+            }
+        }
+        s
+    }
+
+    /// Decodes the pixels in column-major order.
+    pub fn to_mask(&self) -> RleVec<bool> {
+        let mut mask = RleVec::new();
+        for (value, len) in runs(&self.counts) {
+            mask.push_n(len as usize, value);
+        }
+        mask
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the counts, starting with a run of background pixels.
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Returns the number of foreground pixels.
+    pub fn area(&self) -> usize {
+        self.counts.iter().skip(1).step_by(2).map(|&count| count as usize).sum()
+    }
+
+    /// Returns the bounding box of the foreground as `[x, y, width, height]`, or `None` for an
+    /// empty mask.
+    pub fn bbox(&self) -> Option<[usize; 4]> {
+        let h = self.height;
+        let (mut x0, mut x1, mut y0, mut y1) = (usize::MAX, 0, usize::MAX, 0);
+        let mut start = 0;
+        for (value, len) in runs(&self.counts) {
+            let end = start + len as usize - 1;
+            if value {
+                let (first_x, last_x) = (start / h, end / h);
+                x0 = x0.min(first_x);
+                x1 = x1.max(last_x);
+                if first_x == last_x {
+                    y0 = y0.min(start % h);
+                    y1 = y1.max(end % h);
+                } else {
+                    // the run covers the rows of a whole column
+                    y0 = 0;
+                    y1 = h - 1;
+                }
+            }
+            start = end + 1;
+        }
+        if x0 > x1 {
+            return None
+        }
+        Some([x0, y0, x1 + 1 - x0, y1 + 1 - y0])
+    }
+
+    fn combine<F: Fn(bool, bool) -> bool>(&self, other: &CocoRle, f: F) -> CocoRle {
+        assert!(self.height == other.height && self.width == other.width,
+            "the size {}×{} differs from {}×{}", self.height, self.width, other.height, other.width);
+        let mut counts = vec![0];
+        let (mut runs_a, mut runs_b) = (runs(&self.counts), runs(&other.counts));
+        let (mut a, mut b) = (runs_a.next(), runs_b.next());
+        while let (Some((value_a, len_a)), Some((value_b, len_b))) = (a, b) {
+            let len = len_a.min(len_b);
+            push_count(&mut counts, f(value_a, value_b), len);
+            a = if len_a > len { Some((value_a, len_a - len)) } else { runs_a.next() };
+            b = if len_b > len { Some((value_b, len_b - len)) } else { runs_b.next() };
+        }
+        CocoRle { height: self.height, width: self.width, counts }
+    }
+
+    /// Returns the mask of the pixels in the foreground of either mask.
+    ///
+    /// # Panics
+    /// Panics if the masks differ in size.
+    pub fn union(&self, other: &CocoRle) -> CocoRle {
+        self.combine(other, |a, b| a || b)
+    }
+
+    /// Returns the mask of the pixels in the foreground of both masks.
+    ///
+    /// # Panics
+    /// Panics if the masks differ in size.
+    pub fn intersection(&self, other: &CocoRle) -> CocoRle {
+        self.combine(other, |a, b| a && b)
+    }
+
+    /// Returns the intersection over union of the foregrounds, zero if both are empty.
+    ///
+    /// # Panics
+    /// Panics if the masks differ in size.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::coco::CocoRle;
+    /// let a = CocoRle::from_counts(4, 4, &[0, 8, 8]).unwrap();
+    /// let b = CocoRle::from_counts(4, 4, &[4, 8, 4]).unwrap();
+    ///
+    /// assert_eq!(a.iou(&b), 4.0 / 12.0);
+    /// assert_eq!(a.union(&b).area(), 12);
+    /// ```
+    pub fn iou(&self, other: &CocoRle) -> f64 {
+        let intersection = self.intersection(other).area();
+        let union = self.area() + other.area() - intersection;
+        if union == 0 {
+            return 0.0
+        }
+        intersection as f64 / union as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::Rng;
+
+    #[test]
+    fn compressed_strings() {
+        // large differences take several characters, negative ones set the sign bit
+        let rle = CocoRle::from_counts(100, 100, &[1000, 2, 8000, 1, 997]).unwrap();
+        let s = rle.to_compressed();
+        assert_eq!(s, "Xo02Pj7OUUI");
+        assert_eq!(CocoRle::from_compressed(100, 100, &s).unwrap(), rle);
+
+        let all = CocoRle::from_counts(3, 3, &[0, 9]).unwrap();
+        assert_eq!(all.to_compressed(), "09");
+        assert_eq!(all.area(), 9);
+        assert_eq!(all.bbox(), Some([0, 0, 3, 3]));
+        let none = CocoRle::from_counts(3, 3, &[9]).unwrap();
+        assert_eq!(none.bbox(), None);
+        assert_eq!(none.iou(&none), 0.0);
+        assert_eq!(all.iou(&none), 0.0);
+        assert_eq!(all.union(&none), all);
+        assert_eq!(all.intersection(&none), none);
+
+        assert_eq!(CocoRle::from_compressed(3, 3, "0 "), Err(CocoError::InvalidCharacter { offset: 1 }));
+        assert_eq!(CocoRle::from_compressed(3, 3, "0`"), Err(CocoError::Truncated { offset: 1 }));
+        assert_eq!(CocoRle::from_compressed(3, 3, "O"), Err(CocoError::InvalidCount { offset: 0 }));
+        assert_eq!(CocoRle::from_compressed(3, 3, "08"), Err(CocoError::SizeMismatch { expected: 9, found: 8 }));
+        assert_eq!(CocoRle::from_counts(1 << 16, 1 << 16, &[]), Err(CocoError::CountOverflow));
+        let mask: RleVec<bool> = RleVec::new();
+        assert_eq!(CocoRle::from_mask(&mask, 1, 1), Err(CocoError::SizeMismatch { expected: 1, found: 0 }));
+    }
+
+    #[test]
+    fn same_as_pixels() {
+        let mut rng = Rng::new(3);
+
+        let (height, width) = (13, 9);
+        let mask = |rng: &mut Rng| {
+            let mut pixels = Vec::new();
+            while pixels.len() < height * width {
+                let len = (rng.below(20) + 1).min(height * width - pixels.len());
+                let value = rng.below(2) == 0;
+                pixels.extend(std::iter::repeat_n(value, len));
+            }
+            pixels
+        };
+        for _ in 0..100 {
+            let a = mask(&mut rng);
+            let b = mask(&mut rng);
+            let rle_a = CocoRle::from_mask(&a.iter().cloned().collect::<RleVec<bool>>(), height, width).unwrap();
+            let rle_b = CocoRle::from_mask(&b.iter().cloned().collect::<RleVec<bool>>(), height, width).unwrap();
+            assert_eq!(rle_a.to_mask().to_vec(), a);
+            assert_eq!(CocoRle::from_compressed(height, width, &rle_a.to_compressed()).unwrap(), rle_a);
+
+            assert_eq!(rle_a.area(), a.iter().filter(|&&p| p).count());
+            let union: Vec<_> = a.iter().zip(&b).map(|(&p, &q)| p || q).collect();
+            let intersection: Vec<_> = a.iter().zip(&b).map(|(&p, &q)| p && q).collect();
+            assert_eq!(rle_a.union(&rle_b).to_mask().to_vec(), union);
+            assert_eq!(rle_a.intersection(&rle_b).to_mask().to_vec(), intersection);
+            let count = |pixels: &[bool]| pixels.iter().filter(|&&p| p).count() as f64;
+            assert_eq!(rle_a.iou(&rle_b), count(&intersection) / count(&union));
+
+            let columns: Vec<usize> = (0..width).filter(|x| a[x * height..(x + 1) * height].contains(&true)).collect();
+            let rows: Vec<usize> = (0..height).filter(|&y| (0..width).any(|x| a[x * height + y])).collect();
+            let expected = columns.first().map(|&x0| {
+                let y0 = rows[0];
+                [x0, y0, columns[columns.len() - 1] + 1 - x0, rows[rows.len() - 1] + 1 - y0]
+            });
+            assert_eq!(rle_a.bbox(), expected);
+        }
+    }
+}
@@ -0,0 +1,670 @@
+//! Cursors that remember their run for sequential access to an `RleVec`.
+
+use std::cmp;
+use std::fmt;
+use std::mem;
+
+use super::{IndexOverflow, RleIndex, RleVec};
+
+/// Finds the run containing `index`, starting the search at run `from`.
+///
+/// Gallops away from `from` in steps of 1, 2, 4, ... runs until the run is bracketed and then
+/// binary searches the bracket, so a jump over `d` runs costs **O(log d)**.
+pub(crate) fn gallop<I: RleIndex>(ends: &[I], from: usize, index: usize) -> usize {
+    gallop_by(ends.len(), from, index, |run| ends[run].to_usize())
+}
+
+fn gallop_by<F: Fn(usize) -> usize>(runs: usize, from: usize, index: usize, end: F) -> usize {
+    let (mut lo, mut hi) = if end(from) < index {
+        let mut lo = from + 1;
+        let mut step = 1;
+        loop {
+            let probe = from + step;
+            if probe >= runs - 1 || end(probe) >= index {
+                break (lo, probe.min(runs - 1));
+            }
+            lo = probe + 1;
+            step *= 2;
+        }
+    } else if from > 0 && end(from - 1) >= index {
+        let mut hi = from - 1;
+        let mut step = 1;
+        loop {
+            if hi < step {
+                break (0, hi);
+            }
+            let probe = hi - step;
+            if end(probe) < index {
+                break (probe + 1, hi);
+            }
+            hi = probe;
+            step *= 2;
+        }
+    } else {
+        return from;
+    };
+    // the end of run hi is at least index
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if end(mid) < index { lo = mid + 1 } else { hi = mid }
+    }
+    lo
+}
+
+/// A cursor pointing at a position of an `RleVec`.
+///
+/// The cursor remembers the run it is in. Seeking to a nearby index only searches the runs
+/// between the old and the new position, which makes sequential and mostly increasing access
+/// cheaper than [`Index`](https://doc.rust-lang.org/std/ops/trait.Index.html), which searches
+/// all runs on every call.
+///
+/// Can be obtained from the [`cursor`](struct.RleVec.html#method.cursor) method.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleVec;
+/// let rle = RleVec::from(&[1, 1, 1, 2, 2, 3, 3, 3, 3][..]);
+///
+/// let mut cursor = rle.cursor(1);
+/// assert_eq!(*cursor.value(), 1);
+/// assert_eq!((cursor.run_start(), cursor.run_end()), (0, 2));
+///
+/// cursor.seek(6);
+/// assert_eq!(*cursor.value(), 3);
+/// assert_eq!(cursor.run_start(), 5);
+///
+/// assert!(cursor.prev_run());
+/// assert_eq!(cursor.index(), 3);
+/// assert_eq!(*cursor.value(), 2);
+/// ```
+pub struct Cursor<'a, T: 'a, I: 'a = usize> {
+    rle: &'a RleVec<T, I>,
+    run: usize,
+    index: usize,
+}
+
+impl<'a, T: 'a, I: RleIndex> Clone for Cursor<'a, T, I> {
+    fn clone(&self) -> Self {
+        Cursor { rle: self.rle, run: self.run, index: self.index }
+    }
+}
+
+impl<'a, T: 'a, I: RleIndex> Cursor<'a, T, I> {
+    pub(crate) fn new(rle: &'a RleVec<T, I>, index: usize) -> Self {
+        let run = rle.run_index(index);
+        Cursor { rle, run, index }
+    }
+
+    /// Moves the cursor to the given index.
+    ///
+    /// # Panics
+    /// Panics if index is out of bounds.
+    pub fn seek(&mut self, index: usize) {
+        if index >= self.rle.len() {
+            panic!("index out of bounds: the len is {} but the index is {}", self.rle.len(), index)
+        }
+        self.run = gallop(&self.rle.ends, self.run, index);
+        self.index = index;
+    }
+
+    /// Returns the index the cursor points at.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the index of the run the cursor points into.
+    pub fn run_index(&self) -> usize {
+        self.run
+    }
+
+    /// Returns the value at the cursor.
+    pub fn value(&self) -> &'a T {
+        &self.rle.values[self.run]
+    }
+
+    /// Returns the 0-based start coordinate of the current run.
+    pub fn run_start(&self) -> usize {
+        match self.run {
+            0 => 0,
+            run => self.rle.end(run - 1) + 1,
+        }
+    }
+
+    /// Returns the 0-based end coordinate of the current run.
+    pub fn run_end(&self) -> usize {
+        self.rle.end(self.run)
+    }
+
+    /// Moves the cursor to the start of the next run.
+    ///
+    /// Returns `false` and leaves the cursor in place if it is in the last run.
+    pub fn next_run(&mut self) -> bool {
+        if self.run + 1 >= self.rle.runs_len() {
+            return false
+        }
+        self.index = self.run_end() + 1;
+        self.run += 1;
+        true
+    }
+
+    /// Moves the cursor to the start of the previous run.
+    ///
+    /// Returns `false` and leaves the cursor in place if it is in the first run.
+    pub fn prev_run(&mut self) -> bool {
+        if self.run == 0 {
+            return false
+        }
+        self.run -= 1;
+        self.index = self.run_start();
+        true
+    }
+}
+
+/// A cursor that edits an `RleVec` at its position.
+///
+/// The cursor points at an index in `0..=len`, pointing at `len` allows appending. Inserting or
+/// removing a value shifts the ends of all following runs. The cursor applies this shift lazily:
+/// the ends of the runs between the edits are shifted when the cursor moves over them, and the
+/// rest of the runs are shifted once when the cursor is dropped. A series of edits close to each
+/// other therefore costs about as much as a single `insert` on the `RleVec`.
+///
+/// The `RleVec` is only consistent again after the cursor is dropped, leaking the cursor with
+/// `mem::forget` leaves the ends after the last edit unshifted.
+///
+/// Can be obtained from the [`cursor_mut`](struct.RleVec.html#method.cursor_mut) method.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleVec;
+/// let mut rle = RleVec::from(&[0, 0, 0, 0, 1, 1][..]);
+/// {
+///     let mut cursor = rle.cursor_mut(1);
+///     cursor.insert(5);
+///     cursor.insert(5);
+///     assert_eq!(cursor.index(), 3);
+///     assert_eq!(cursor.remove(), 0);
+///     cursor.set(2);
+/// }
+/// assert_eq!(rle.to_vec(), vec![0, 5, 5, 2, 0, 1, 1]);
+/// ```
+pub struct CursorMut<'a, T: 'a, I: RleIndex + 'a = usize> {
+    rle: &'a mut RleVec<T, I>,
+    run: usize,
+    index: usize,
+    len: usize,
+    // the ends of the runs from `pending` on need `delta` added
+    pending: usize,
+    delta: isize,
+}
+
+impl<'a, T: 'a, I: RleIndex> CursorMut<'a, T, I> {
+    pub(crate) fn new(rle: &'a mut RleVec<T, I>, index: usize) -> Self {
+        let len = rle.len();
+        assert!(index <= len, "cursor index (is {}) should be <= len (is {})", index, len);
+        let run = if index == len { rle.runs_len() } else { rle.run_index(index) };
+        let pending = rle.runs_len();
+        CursorMut { rle, run, index, len, pending, delta: 0 }
+    }
+
+    /// Returns the index the cursor points at.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the index of the run the cursor points into, or the number of runs if the cursor
+    /// points past the end.
+    pub fn run_index(&self) -> usize {
+        self.run
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the value at the cursor, or `None` if the cursor points past the end.
+    pub fn value(&self) -> Option<&T> {
+        self.rle.values.get(self.run)
+    }
+
+    /// Moves the cursor to the given index.
+    ///
+    /// # Panics
+    /// Panics if `index > len`.
+    pub fn seek(&mut self, index: usize) {
+        assert!(index <= self.len, "cursor index (is {}) should be <= len (is {})", index, self.len);
+        self.index = index;
+        self.locate();
+    }
+
+    fn locate(&mut self) {
+        let runs = self.rle.runs_len();
+        self.run = if self.index == self.len {
+            runs
+        } else {
+            let from = cmp::min(self.run, runs - 1);
+            gallop_by(runs, from, self.index, |run| self.end(run))
+        };
+    }
+
+    fn end(&self, run: usize) -> usize {
+        let end = self.rle.end(run);
+        if run >= self.pending { (end as isize + self.delta) as usize } else { end }
+    }
+
+    fn start(&self, run: usize) -> usize {
+        match run {
+            0 => 0,
+            run => self.end(run - 1) + 1,
+        }
+    }
+
+    /// Applies the pending shift to the ends of the runs before `upto`.
+    fn materialize(&mut self, upto: usize) {
+        let upto = cmp::min(upto, self.rle.runs_len());
+        if self.delta == 0 {
+            self.pending = self.rle.runs_len();
+        } else if self.pending < upto {
+            for run in self.pending..upto {
+                let end = self.end(run);
+                self.rle.set_end(run, end);
+            }
+            self.pending = upto;
+        }
+    }
+
+    /// Shifts the ends of the runs from `from_run` on, the ends of the two runs at `from_run`
+    /// are shifted right away.
+    fn shift(&mut self, from_run: usize, by: isize) {
+        if self.delta == 0 {
+            self.pending = from_run;
+        } else {
+            self.materialize(from_run);
+            for run in from_run..self.pending {
+                let end = self.rle.end(run);
+                self.rle.set_end(run, (end as isize + by) as usize);
+            }
+        }
+        self.delta += by;
+        self.materialize(from_run + 2);
+    }
+
+    fn set_end(&mut self, run: usize, end: usize) {
+        debug_assert!(run < self.pending);
+        self.rle.set_end(run, end);
+    }
+
+    fn insert_run(&mut self, run: usize, end: usize, value: T) {
+        debug_assert!(run <= self.pending);
+        self.rle.insert_run(run, end, value);
+        self.pending += 1;
+    }
+
+    fn remove_run(&mut self, run: usize) -> T {
+        debug_assert!(run < self.pending);
+        self.pending -= 1;
+        self.rle.remove_run(run)
+    }
+}
+
+impl<'a, T: Eq + 'a, I: RleIndex> CursorMut<'a, T, I> {
+    /// Replaces the value of the whole run at the cursor and returns the old value.
+    ///
+    /// The run is merged with its neighbours if they hold the new value.
+    ///
+    /// # Panics
+    /// Panics if the cursor points past the end.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[1, 2, 2, 2, 1, 3][..]);
+    /// assert_eq!(rle.cursor_mut(2).replace_run_value(1), 2);
+    ///
+    /// assert_eq!(rle.to_vec(), vec![1, 1, 1, 1, 1, 3]);
+    /// assert_eq!(rle.runs_len(), 2);
+    /// ```
+    pub fn replace_run_value(&mut self, value: T) -> T {
+        assert!(self.index < self.len, "the cursor points past the end (len is {})", self.len);
+        let run = self.run;
+        self.materialize(run + 2);
+        let old = mem::replace(&mut self.rle.values[run], value);
+
+        if run + 1 < self.rle.runs_len() && self.rle.values[run + 1] == self.rle.values[run] {
+            let end = self.end(run + 1);
+            self.remove_run(run + 1);
+            self.set_end(run, end);
+        }
+        if run > 0 && self.rle.values[run - 1] == self.rle.values[run] {
+            let end = self.end(run);
+            self.remove_run(run);
+            self.set_end(run - 1, end);
+        }
+        self.locate();
+        old
+    }
+}
+
+impl<'a, T: Eq + Clone + 'a, I: RleIndex> CursorMut<'a, T, I> {
+    /// Modifies the value at the cursor.
+    ///
+    /// # Panics
+    /// Panics if the cursor points past the end.
+    pub fn set(&mut self, value: T) {
+        assert!(self.index < self.len, "the cursor points past the end (len is {})", self.len);
+        let (p, index) = (self.run, self.index);
+        if self.rle.values[p] == value { return }
+        self.materialize(p + 2);
+        let (start, end) = (self.start(p), self.end(p));
+        let prev_equal = p > 0 && self.rle.values[p - 1] == value;
+        let next_equal = p + 1 < self.rle.runs_len() && self.rle.values[p + 1] == value;
+
+        if start == end {
+            match (prev_equal, next_equal) {
+                (true, true) => {
+                    let next_end = self.end(p + 1);
+                    self.remove_run(p + 1);
+                    self.remove_run(p);
+                    self.set_end(p - 1, next_end);
+                }
+                (true, false) => {
+                    self.remove_run(p);
+                    self.set_end(p - 1, end);
+                }
+                (false, true) => { self.remove_run(p); }
+                (false, false) => self.rle.values[p] = value,
+            }
+        } else if index == start {
+            if prev_equal {
+                self.set_end(p - 1, start);
+            } else {
+                self.insert_run(p, start, value);
+            }
+        } else if index == end {
+            self.set_end(p, end - 1);
+            if !next_equal {
+                self.insert_run(p + 1, end, value);
+            }
+        } else {
+            let old = self.rle.values[p].clone();
+            self.set_end(p, index - 1);
+            self.insert_run(p + 1, index, value);
+            self.insert_run(p + 2, end, old);
+        }
+        self.locate();
+    }
+
+    /// Inserts a value at the cursor and moves the cursor past it.
+    ///
+    /// # Panics
+    /// Panics if the number of elements overflows the index type.
+    #[inline]
+    pub fn insert(&mut self, value: T) {
+        self.insert_n(1, value)
+    }
+
+    /// Inserts the same value n times at the cursor and moves the cursor past them.
+    ///
+    /// # Panics
+    /// Panics if the number of elements overflows the index type.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[0, 0, 1][..]);
+    /// {
+    ///     let mut cursor = rle.cursor_mut(1);
+    ///     cursor.insert_n(3, 2);
+    ///     cursor.insert_n(2, 0);
+    /// }
+    /// assert_eq!(rle.to_vec(), vec![0, 2, 2, 2, 0, 0, 0, 1]);
+    /// ```
+    pub fn insert_n(&mut self, n: usize, value: T) {
+        if n == 0 { return }
+        match self.len.checked_add(n - 1).and_then(I::from_usize) {
+            Some(_) => (),
+            None => panic!("{}", IndexOverflow),
+        }
+
+        let (p, index) = (self.run, self.index);
+        let by = n as isize;
+        self.materialize(p + 2);
+        if index == self.len {
+            if p > 0 && self.rle.values[p - 1] == value {
+                self.shift(p - 1, by);
+            } else {
+                self.insert_run(p, index + n - 1, value);
+            }
+        } else if self.rle.values[p] == value {
+            self.shift(p, by);
+        } else {
+            let (start, end) = (self.start(p), self.end(p));
+            if index == start && p > 0 && self.rle.values[p - 1] == value {
+                self.shift(p - 1, by);
+            } else if index == start {
+                self.shift(p, by);
+                self.insert_run(p, index + n - 1, value);
+            } else {
+                let old = self.rle.values[p].clone();
+                self.shift(p, by);
+                self.set_end(p, index - 1);
+                self.insert_run(p + 1, index + n - 1, value);
+                self.insert_run(p + 2, end + n, old);
+            }
+        }
+        self.len += n;
+        self.index += n;
+        self.locate();
+    }
+
+    /// Removes and returns the value at the cursor, the cursor then points at the next value.
+    ///
+    /// # Panics
+    /// Panics if the cursor points past the end.
+    pub fn remove(&mut self) -> T {
+        assert!(self.index < self.len, "the cursor points past the end (len is {})", self.len);
+        let p = self.run;
+        self.materialize(p + 2);
+        let value = if self.start(p) == self.end(p) {
+            let value = self.remove_run(p);
+            self.shift(p, -1);
+            if p > 0 && p < self.rle.runs_len() && self.rle.values[p - 1] == self.rle.values[p] {
+                let end = self.end(p);
+                self.remove_run(p);
+                self.set_end(p - 1, end);
+            }
+            value
+        } else {
+            self.shift(p, -1);
+            self.rle.values[p].clone()
+        };
+        self.len -= 1;
+        self.run = p.saturating_sub(1);
+        self.locate();
+        value
+    }
+
+    /// Splits the run at the cursor so that a new run starts at the cursor.
+    ///
+    /// Both parts keep the value of the run, so the vector holds two adjacent equal runs until
+    /// they are merged again, for example by [`coalesce`](struct.RleVec.html#method.coalesce).
+    /// The comparisons and the hash of `RleVec` treat them like a single run. Returns `false` if
+    /// the cursor already is at the start of a run or past the end.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[1, 1, 1, 1][..]);
+    /// assert!(rle.cursor_mut(2).split_run_here());
+    ///
+    /// assert_eq!(rle.to_vec(), vec![1, 1, 1, 1]);
+    /// assert_eq!(rle.ends(), &[1, 3]);
+    /// ```
+    pub fn split_run_here(&mut self) -> bool {
+        let p = self.run;
+        if self.index == self.len || self.index == self.start(p) {
+            return false
+        }
+        self.materialize(p + 1);
+        let value = self.rle.values[p].clone();
+        let index = self.index;
+        self.insert_run(p, index - 1, value);
+        self.run = p + 1;
+        true
+    }
+}
+
+impl<'a, T: 'a, I: RleIndex> Drop for CursorMut<'a, T, I> {
+    fn drop(&mut self) {
+        self.materialize(self.rle.runs_len());
+    }
+}
+
+impl<'a, T: fmt::Debug + 'a, I: RleIndex> fmt::Debug for Cursor<'a, T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cursor").field("index", &self.index).field("value", self.value()).finish()
+    }
+}
+
+impl<'a, T: fmt::Debug + 'a, I: RleIndex> fmt::Debug for CursorMut<'a, T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CursorMut").field("index", &self.index).field("value", &self.value()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::Rng;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    #[test]
+    fn gallop_matches_binary_search() {
+        let rle: RleVec<_> = (0..200usize).flat_map(|i| std::iter::repeat_n(i, i % 7 + 1)).collect();
+        for from in 0..rle.runs_len() {
+            for index in 0..rle.len() {
+                assert_eq!(gallop(&rle.ends, from, index), rle.run_index(index), "{} {}", from, index);
+            }
+        }
+    }
+
+    #[test]
+    fn seek_and_step() {
+        let v = vec![0, 0, 1, 2, 2, 2, 0, 1, 1];
+        let rle = RleVec::from(&v[..]);
+        let mut cursor = rle.cursor(0);
+        for (i, value) in v.iter().enumerate().rev().chain(v.iter().enumerate()) {
+            cursor.seek(i);
+            assert_eq!(cursor.value(), value);
+            assert!(cursor.run_start() <= i && i <= cursor.run_end());
+        }
+
+        let mut cursor = rle.cursor(1);
+        let mut starts = vec![cursor.run_start()];
+        while cursor.next_run() {
+            assert_eq!(cursor.index(), cursor.run_start());
+            starts.push(cursor.index());
+        }
+        assert_eq!(starts, rle.starts());
+        assert_eq!(cursor.run_end(), 8);
+        assert_eq!(cursor.run_index(), 4);
+
+        while cursor.prev_run() {}
+        assert_eq!((cursor.index(), cursor.run_index()), (0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn seek_out_of_bounds() {
+        let rle = RleVec::from(&[1, 2][..]);
+        rle.cursor(0).seek(2);
+    }
+
+    #[test]
+    fn cursor_mut_same_as_vec() {
+        let mut rng = Rng::new(11);
+
+        let mut v: Vec<u8> = (0..300).map(|_| (rng.below(6) / 4) as u8).collect();
+        let mut rle: RleVec<u8, u32> = v.iter().cloned().collect();
+        for _ in 0..50 {
+            {
+                let mut cursor = rle.cursor_mut(rng.below(v.len() + 1));
+                for _ in 0..40 {
+                    // mostly small jumps in both directions
+                    let index = match rng.below(5) {
+                        0 => rng.below(v.len() + 1),
+                        _ => cmp::min(v.len(), (cursor.index() + rng.below(8)).saturating_sub(3)),
+                    };
+                    cursor.seek(index);
+                    assert_eq!(cursor.value(), v.get(index));
+                    let value = rng.below(3) as u8;
+                    match rng.below(5) {
+                        0 => {
+                            let n = rng.below(3);
+                            cursor.insert_n(n, value);
+                            v.splice(index..index, std::iter::repeat_n(value, n));
+                            assert_eq!(cursor.index(), index + n);
+                        }
+                        1 if index < v.len() => assert_eq!(cursor.remove(), v.remove(index)),
+                        2 if index < v.len() => {
+                            cursor.set(value);
+                            v[index] = value;
+                        }
+                        3 if index < v.len() => {
+                            let old = v[index];
+                            let (start, end) = (cursor.start(cursor.run), cursor.end(cursor.run));
+                            assert_eq!(cursor.replace_run_value(value), old);
+                            for x in &mut v[start..=end] { *x = value; }
+                        }
+                        _ => {
+                            cursor.insert(value);
+                            v.insert(index, value);
+                        }
+                    }
+                    assert_eq!(cursor.len(), v.len());
+                }
+            }
+            // the edits leave no equal neighbouring runs behind, not only an equal comparison
+            let expected: RleVec<u8, u32> = v.iter().cloned().collect();
+            assert_eq!(rle, expected);
+            assert!(rle.is_canonical());
+            assert_eq!(rle.ends(), expected.ends());
+        }
+    }
+
+    #[test]
+    fn cursor_mut_split() {
+        let mut rle = RleVec::from(&[1, 1, 1, 2, 2][..]);
+        {
+            let mut cursor = rle.cursor_mut(0);
+            cursor.insert(7);
+            assert!(!cursor.split_run_here());
+            cursor.seek(2);
+            assert!(cursor.split_run_here());
+            assert_eq!(cursor.value(), Some(&1));
+            cursor.seek(6);
+            assert!(!cursor.split_run_here());
+        }
+        assert_eq!(rle.to_vec(), vec![7, 1, 1, 1, 2, 2]);
+        assert_eq!(rle.ends(), &[0, 1, 3, 5]);
+
+        // the split runs compare and hash like the merged ones
+        let hash = |rle: &RleVec<i32>| {
+            let mut hasher = DefaultHasher::new();
+            rle.hash(&mut hasher);
+            hasher.finish()
+        };
+        let merged = RleVec::from(&[7, 1, 1, 1, 2, 2][..]);
+        assert_eq!(rle, merged);
+        assert_eq!(rle.cmp(&merged), cmp::Ordering::Equal);
+        assert_eq!(hash(&rle), hash(&merged));
+        assert_ne!(rle, RleVec::from(&[7, 1, 1, 2, 2, 2][..]));
+        assert!(rle < RleVec::from(&[7, 1, 1, 1, 1, 2][..]));
+    }
+}
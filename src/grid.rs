@@ -0,0 +1,363 @@
+//! A two-dimensional grid storing every row as an `RleVec`.
+
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Index, Range};
+use std::slice;
+
+use coco::{self, CocoError, CocoRle};
+use super::{push_slice, RleVec};
+
+/// The error returned when the rows or counts of a grid do not fit its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridError {
+    /// The length of a row differs from the width.
+    RowLength {
+        /// The index of the row.
+        row: usize,
+        /// The length of the row.
+        len: usize,
+        /// The width of the grid.
+        width: usize,
+    },
+    /// The counts sum up to another number of cells than the grid holds.
+    CountsLength {
+        /// The number of cells of the grid.
+        expected: usize,
+        /// The sum of the counts.
+        found: usize,
+    },
+    /// The number of cells does not fit in a `u32` count.
+    CountOverflow,
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GridError::RowLength { row, len, width } =>
+                write!(f, "row {} has a length of {} instead of the width {}", row, len, width),
+            GridError::CountsLength { expected, found } =>
+                write!(f, "the counts cover {} cells instead of {}", found, expected),
+            GridError::CountOverflow => write!(f, "the number of cells overflows the counts"),
+        }
+    }
+}
+
+impl Error for GridError {}
+
+/// A grid of `width` × `height` values, storing every row as an `RleVec`.
+///
+/// Masks and sprites usually hold long horizontal runs, so every row keeps its own runs and
+/// accessing a cell is a binary search in its row. The rows can be written with the BMP and
+/// TGA codecs of this crate, and `bool` masks convert from and to a
+/// [`CocoRle`](coco/struct.CocoRle.html).
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleGrid;
+/// let mut grid = RleGrid::new(8, 4, 0u8);
+/// grid.set_rect(2..6, 1..3, 9);
+///
+/// assert_eq!(grid[(2, 1)], 9);
+/// assert_eq!(grid.get(6, 1), Some(&0));
+/// assert_eq!(grid.get(8, 0), None);
+/// assert_eq!(grid.row(1).unwrap().to_string(), "[0×2, 9×4, 0×2]");
+///
+/// let crop = grid.crop(4..8, 0..2);
+/// assert_eq!(crop.rows().map(|row| row.to_vec()).collect::<Vec<_>>(),
+///            vec![vec![0, 0, 0, 0], vec![9, 9, 0, 0]]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RleGrid<T> {
+    width: usize,
+    rows: Vec<RleVec<T>>,
+}
+
+impl<T> RleGrid<T> {
+    /// Constructs a grid of the given rows, which all have a length of `width`.
+    ///
+    /// Returns an error for a row of another length.
+    pub fn from_rows(width: usize, rows: Vec<RleVec<T>>) -> Result<RleGrid<T>, GridError> {
+        for (row, rle) in rows.iter().enumerate() {
+            if rle.len() != width {
+                return Err(GridError::RowLength { row, len: rle.len(), width })
+            }
+        }
+        Ok(RleGrid { width, rows })
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the value at column `x` of row `y`, or `None` if it is out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        match self.rows.get(y) {
+            Some(row) if x < self.width => Some(&row[x]),
+            _ => None,
+        }
+    }
+
+    /// Returns the row `y`, or `None` if it is out of bounds.
+    pub fn row(&self, y: usize) -> Option<&RleVec<T>> {
+        self.rows.get(y)
+    }
+
+    /// Returns an iterator over the rows from top to bottom.
+    pub fn rows(&self) -> slice::Iter<'_, RleVec<T>> {
+        self.rows.iter()
+    }
+
+    /// Returns the rows.
+    pub fn into_rows(self) -> Vec<RleVec<T>> {
+        self.rows
+    }
+
+    /// Returns the total number of runs of all rows.
+    pub fn runs_len(&self) -> usize {
+        self.rows.iter().map(RleVec::runs_len).sum()
+    }
+}
+
+fn slice_row<T: Eq + Clone>(row: &RleVec<T>, range: Range<usize>) -> RleVec<T> {
+    let mut sliced = RleVec::new();
+    push_slice(&mut sliced, row, range);
+    sliced
+}
+
+impl<T: Eq + Clone> RleGrid<T> {
+    /// Constructs a grid of `width` × `height` copies of `value`.
+    pub fn new(width: usize, height: usize, value: T) -> RleGrid<T> {
+        let mut row = RleVec::new();
+        row.push_n(width, value);
+        RleGrid { width, rows: vec![row; height] }
+    }
+
+    /// Sets the value at column `x` of row `y`.
+    ///
+    /// # Panics
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn set(&mut self, x: usize, y: usize, value: T) {
+        assert!(x < self.width, "column {} out of bounds for width {}", x, self.width);
+        self.rows[y].set(x, value)
+    }
+
+    /// Sets the values in the columns `x` of the rows `y`.
+    ///
+    /// # Panics
+    /// Panics if a range ends after the width or height.
+    pub fn set_rect(&mut self, x: Range<usize>, y: Range<usize>, value: T) {
+        assert!(x.end <= self.width, "column {} out of bounds for width {}", x.end, self.width);
+        assert!(y.end <= self.rows.len(), "row {} out of bounds for height {}", y.end, self.rows.len());
+        if x.start >= x.end {
+            return
+        }
+        for row in &mut self.rows[y] {
+            let mut rle = slice_row(row, 0..x.start);
+            rle.push_n(x.end - x.start, value.clone());
+            push_slice(&mut rle, row, x.end..self.width);
+            *row = rle;
+        }
+    }
+
+    /// Returns the part of the grid in the columns `x` of the rows `y`.
+    ///
+    /// # Panics
+    /// Panics if a range ends after the width or height.
+    pub fn crop(&self, x: Range<usize>, y: Range<usize>) -> RleGrid<T> {
+        assert!(x.end <= self.width, "column {} out of bounds for width {}", x.end, self.width);
+        assert!(y.end <= self.rows.len(), "row {} out of bounds for height {}", y.end, self.rows.len());
+        let width = x.end.saturating_sub(x.start);
+        let rows = self.rows[y].iter().map(|row| slice_row(row, x.clone())).collect();
+        RleGrid { width, rows }
+    }
+
+    /// Returns the grid with the rows and columns swapped.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleGrid, RleVec};
+    /// let rows = vec![RleVec::from(&[1, 1, 2][..]), RleVec::from(&[3, 3, 3][..])];
+    /// let grid = RleGrid::from_rows(3, rows).unwrap();
+    ///
+    /// let transposed = grid.transpose();
+    /// assert_eq!((transposed.width(), transposed.height()), (2, 3));
+    /// assert_eq!(transposed.row(2).unwrap().to_vec(), vec![2, 3]);
+    /// ```
+    pub fn transpose(&self) -> RleGrid<T> {
+        let mut iters: Vec<_> = self.rows.iter().map(|row| row.iter()).collect();
+        let rows = (0..self.width)
+            .map(|_| iters.iter_mut().map(|iter| iter.next().expect("a row of the width").clone()).collect())
+            .collect();
+        RleGrid { width: self.rows.len(), rows }
+    }
+}
+
+// the counts of a grid only fail on its size
+fn counts_error(error: CocoError) -> GridError {
+    match error {
+        CocoError::SizeMismatch { expected, found } => GridError::CountsLength { expected, found },
+        _ => GridError::CountOverflow,
+    }
+}
+
+impl RleGrid<bool> {
+    /// Encodes a mask in the run-length encoding of the COCO dataset, which flattens the
+    /// pixels in column-major order.
+    ///
+    /// Returns an error if the number of pixels does not fit in a `u32` count.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleGrid;
+    /// let mut mask = RleGrid::new(3, 2, false);
+    /// mask.set_rect(1..3, 0..1, true);
+    ///
+    /// // the columns are [false, false], [true, false] and [true, false]
+    /// let rle = mask.to_coco().unwrap();
+    /// assert_eq!(rle.counts(), &[2, 1, 1, 1, 1]);
+    /// assert_eq!(RleGrid::from_coco(&rle), mask);
+    /// ```
+    pub fn to_coco(&self) -> Result<CocoRle, CocoError> {
+        coco::pixels(self.rows.len(), self.width)?;
+        let mut pixels = RleVec::new();
+        for column in self.transpose().rows() {
+            push_slice(&mut pixels, column, 0..self.rows.len());
+        }
+        CocoRle::from_mask(&pixels, self.rows.len(), self.width)
+    }
+
+    /// Decodes a mask in the run-length encoding of the COCO dataset.
+    pub fn from_coco(rle: &CocoRle) -> RleGrid<bool> {
+        let (height, width) = (rle.height(), rle.width());
+        let pixels = rle.to_mask();
+        let columns = (0..width).map(|x| slice_row(&pixels, x * height..(x + 1) * height)).collect();
+        RleGrid { width: height, rows: columns }.transpose()
+    }
+
+    /// Returns the COCO counts of a mask, the lengths of the alternating runs of `false` and
+    /// `true` in column-major order, starting with a run of `false`.
+    ///
+    /// Returns an error if the number of cells does not fit in a `u32` count.
+    pub fn to_coco_counts(&self) -> Result<Vec<u32>, GridError> {
+        self.to_coco().map(|rle| rle.counts().to_vec()).map_err(counts_error)
+    }
+
+    /// Constructs a mask of COCO counts, the lengths of the alternating runs of `false` and
+    /// `true` in column-major order, starting with a run of `false`.
+    ///
+    /// Returns an error if the counts do not sum up to `width * height` or the number of cells
+    /// does not fit in a `u32` count.
+    pub fn from_coco_counts(width: usize, height: usize, counts: &[u32])
+        -> Result<RleGrid<bool>, GridError>
+    {
+        CocoRle::from_counts(height, width, counts).map(|rle| RleGrid::from_coco(&rle)).map_err(counts_error)
+    }
+}
+
+// the hash of an RleVec merges equal runs, which needs to compare the values
+impl<T: Hash + PartialEq> Hash for RleGrid<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.width.hash(state);
+        self.rows.hash(state);
+    }
+}
+
+impl<T> Index<(usize, usize)> for RleGrid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        match self.get(x, y) {
+            Some(value) => value,
+            None => panic!("cell ({}, {}) out of bounds for size {}×{}", x, y, self.width, self.rows.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::same_as;
+
+    #[test]
+    fn same_as_cells() {
+        let (width, height) = (37, 23);
+        let mut cells = vec![vec![0u8; width]; height];
+        let mut grid = RleGrid::new(width, height, 0u8);
+        same_as(5, 300, &mut cells, &mut grid, |rng, cells, grid| {
+            let value = rng.below(3) as u8;
+            if rng.below(2) == 0 {
+                let (x, y) = (rng.below(width), rng.below(height));
+                cells[y][x] = value;
+                grid.set(x, y, value);
+            } else {
+                let (x0, y0) = (rng.below(width + 1), rng.below(height + 1));
+                let (x1, y1) = (x0 + rng.below(width + 1 - x0), y0 + rng.below(height + 1 - y0));
+                for row in &mut cells[y0..y1] {
+                    for cell in &mut row[x0..x1] {
+                        *cell = value;
+                    }
+                }
+                grid.set_rect(x0..x1, y0..y1, value);
+            }
+        }, |_, grid| assert!(grid.rows().all(RleVec::is_canonical)));
+        for (y, row) in cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                assert_eq!(grid[(x, y)], *cell);
+            }
+        }
+
+        let transposed = grid.transpose();
+        for x in 0..width {
+            assert_eq!(transposed.row(x).unwrap().to_vec(), cells.iter().map(|row| row[x]).collect::<Vec<_>>());
+        }
+        assert_eq!(transposed.transpose(), grid);
+
+        let crop = grid.crop(5..30, 10..23);
+        assert_eq!((crop.width(), crop.height()), (25, 13));
+        for (row, cells) in crop.rows().zip(&cells[10..]) {
+            assert_eq!(row.to_vec(), &cells[5..30]);
+        }
+        assert_eq!(grid.crop(3..3, 0..4).rows().map(RleVec::len).collect::<Vec<_>>(), vec![0; 4]);
+
+        let mask = RleGrid::from_rows(width, grid.rows().map(|row| row.iter().map(|&v| v == 1).collect()).collect()).unwrap();
+        let rle = mask.to_coco().unwrap();
+        assert_eq!((rle.height(), rle.width()), (height, width));
+        assert_eq!(rle.area(), cells.iter().flatten().filter(|&&v| v == 1).count());
+        assert_eq!(RleGrid::from_coco(&rle), mask);
+    }
+
+    #[test]
+    fn coco_masks() {
+        let all = RleGrid::new(4, 2, true);
+        assert_eq!(all.to_coco().unwrap().counts(), &[0, 8]);
+        assert_eq!(RleGrid::new(4, 2, false).to_coco().unwrap().counts(), &[8]);
+        let rle = CocoRle::from_counts(2, 4, &[0, 8]).unwrap();
+        assert_eq!(RleGrid::from_coco(&rle), all);
+        let rle = CocoRle::from_counts(4, 2, &[1, 2, 5]).unwrap();
+        assert_eq!(RleGrid::from_coco(&rle).rows().map(|row| row.to_vec()).collect::<Vec<_>>(),
+                   vec![vec![false, false], vec![true, false], vec![true, false], vec![false, false]]);
+
+        assert_eq!(all.to_coco_counts(), Ok(vec![0, 8]));
+        assert_eq!(RleGrid::from_coco_counts(4, 2, &[0, 8]), Ok(all));
+        assert_eq!(RleGrid::from_coco_counts(4, 2, &[0, 7]), Err(GridError::CountsLength { expected: 8, found: 7 }));
+
+        // the cells overflow usize or the u32 counts
+        let huge = usize::MAX / 2 + 1;
+        assert_eq!(RleGrid::new(huge, 2, false).to_coco_counts(), Err(GridError::CountOverflow));
+        assert_eq!(RleGrid::from_coco_counts(huge, 2, &[0]), Err(GridError::CountOverflow));
+        assert_eq!(RleGrid::from_coco_counts(1 << 16, 1 << 16, &[0]), Err(GridError::CountOverflow));
+
+        let rows = vec![RleVec::from(&[1, 2][..]), RleVec::from(&[3][..])];
+        let error = RleGrid::from_rows(2, rows).unwrap_err();
+        assert_eq!(error, GridError::RowLength { row: 1, len: 1, width: 2 });
+        assert_eq!(error.to_string(), "row 1 has a length of 1 instead of the width 2");
+    }
+}
@@ -0,0 +1,129 @@
+//! Integer types used to store the run ends of an `RleVec`.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+/// An unsigned integer type that stores the end positions of the runs in an `RleVec`.
+///
+/// For small values like `u8` or `bool` the run ends dominate the memory usage of an `RleVec`.
+/// On 64-bit targets using `u32` instead of the default `usize` halves the size of a run end,
+/// as long as the vector never holds more than `u32::MAX + 1` elements.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleVec;
+/// let mut rle = RleVec::<bool, u16>::default();
+/// rle.push_n(65_535, true);
+/// rle.push(false);
+///
+/// assert_eq!(rle.len(), 65_536);
+/// assert!(rle.try_push(false).is_err());
+/// ```
+pub trait RleIndex: Copy + Ord + Hash + fmt::Debug + Default {
+    /// Converts a position into the index type, returns `None` if it does not fit.
+    fn from_usize(value: usize) -> Option<Self>;
+
+    /// Converts the index back into a position.
+    fn to_usize(self) -> usize;
+}
+
+macro_rules! impl_rle_index {
+    ($($t:ty),*) => {
+        $(
+            impl RleIndex for $t {
+                #[inline]
+                fn from_usize(value: usize) -> Option<Self> {
+                    <$t>::try_from(value).ok()
+                }
+
+                #[inline]
+                fn to_usize(self) -> usize {
+                    // only values converted from a usize are stored
+                    self as usize
+                }
+            }
+        )*
+    }
+}
+
+impl_rle_index!(u16, u32, u64, usize);
+
+/// The error returned when the length of an `RleVec` no longer fits its index type.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleVec;
+/// let mut rle = RleVec::<u8, u16>::default();
+/// rle.push_n(65_536, 0);
+///
+/// let error = rle.try_insert(10, 1).unwrap_err();
+/// assert_eq!(error.to_string(), "the length of the RleVec overflows its index type");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexOverflow;
+
+impl fmt::Display for IndexOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the length of the RleVec overflows its index type")
+    }
+}
+
+impl Error for IndexOverflow {}
+
+/// The error returned when the run ends and values of an `RleVec` are inconsistent.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, InvalidRuns};
+/// let rle: RleVec<u8> = RleVec::new();
+/// assert_eq!(rle.validate(), Ok(()));
+///
+/// let error = InvalidRuns::UnorderedEnds { run: 2, end: 4, previous_end: 4 };
+/// assert_eq!(error.to_string(), "the end of run 2 (4) is not after the end of the previous run (4)");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidRuns {
+    /// The number of run ends differs from the number of values.
+    LengthMismatch {
+        /// The number of run ends.
+        ends: usize,
+        /// The number of values.
+        values: usize,
+    },
+    /// A run does not end after the previous run, so it is empty or out of order.
+    UnorderedEnds {
+        /// The index of the offending run.
+        run: usize,
+        /// The end of the offending run.
+        end: usize,
+        /// The end of the run before it.
+        previous_end: usize,
+    },
+    /// A run has a length of zero.
+    EmptyRun {
+        /// The index of the empty run.
+        run: usize,
+    },
+    /// The total length of the runs overflows the index type or `usize`.
+    IndexOverflow,
+}
+
+impl fmt::Display for InvalidRuns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InvalidRuns::LengthMismatch { ends, values } => {
+                write!(f, "found {} run ends but {} values", ends, values)
+            }
+            InvalidRuns::UnorderedEnds { run, end, previous_end } => {
+                write!(f, "the end of run {} ({}) is not after the end of the previous run ({})",
+                    run, end, previous_end)
+            }
+            InvalidRuns::EmptyRun { run } => write!(f, "run {} has a length of zero", run),
+            InvalidRuns::IndexOverflow => fmt::Display::fmt(&IndexOverflow, f),
+        }
+    }
+}
+
+impl Error for InvalidRuns {}
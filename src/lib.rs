@@ -12,19 +12,72 @@
 //! |        |push|index   |set with breaking a run|set without breaking a run|insert with breaking a run|insert without breaking a run|
 //! |--------|----|--------|-----------------------|--------------------------|--------------------------|-----------------------------|
 //! |`RleVec`|O(1)|O(log&nbsp;n)|O((log&nbsp;n)&nbsp;+&nbsp;2n)|O(log&nbsp;n)|O((log&nbsp;n)&nbsp;+&nbsp;2n)|O((log&nbsp;n)&nbsp;+&nbsp;n)|
+//! |`ChunkedRleVec`|O(1)|O(log&nbsp;n)|O((log&nbsp;n)&nbsp;+&nbsp;√n)|O(log&nbsp;n)|O((log&nbsp;n)&nbsp;+&nbsp;√n)|O((log&nbsp;n)&nbsp;+&nbsp;√n)|
+//! |`RleTree`|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|
 //! |`Vec`|O(1)|O(1)|O(1)*| |O(n)| |
 //!
+//! For vectors that are heavily edited the [`RleTree`](struct.RleTree.html) stores the runs in a
+//! balanced tree, which avoids shifting the positions of all following runs. The
+//! [`ChunkedRleVec`](chunked/struct.ChunkedRleVec.html) is a middle ground that keeps the runs in
+//! blocks of about √n runs and only shifts the start offsets of the following blocks.
+//!
 #[cfg(feature = "serde")]
 extern crate serde;
-#[cfg(feature = "serde")]
-#[macro_use]
-extern crate serde_derive;
-
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_test;
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_buffer;
+#[cfg(feature = "arrow")]
+extern crate arrow_schema;
+
+use std::convert::TryFrom;
 use std::io;
 use std::iter::FromIterator;
-use std::iter::{once, repeat};
+use std::iter::{once, repeat_n};
 use std::cmp;
-use std::ops::Index;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::ops::{self, Index};
+
+mod binary;
+mod builder;
+mod cursor;
+mod index;
+pub mod packbits;
+pub mod bmp;
+pub mod tga;
+pub mod parquet;
+pub mod bed;
+pub mod coco;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+mod reader;
+mod text;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(test)]
+mod test_util;
+pub mod tree;
+pub mod chunked;
+pub mod sparse;
+pub mod string;
+pub mod map;
+pub mod grid;
+
+pub use binary::{RleValueCodec, FormatError};
+pub use builder::{RleBuilder, Quantizable, Tolerance, Representative};
+pub use index::{RleIndex, IndexOverflow, InvalidRuns};
+pub use reader::RleReader;
+pub use text::ParseRleError;
+pub use tree::RleTree;
+pub use chunked::ChunkedRleVec;
+pub use sparse::SparseRle;
+pub use string::RleString;
+pub use map::{RleMap, Step};
+pub use grid::{RleGrid, GridError};
+pub use cursor::{Cursor, CursorMut};
 
 /// The `RleVec` struct handles like a normal vector and supports a subset from the `Vec` methods.
 ///
@@ -122,10 +175,32 @@ use std::ops::Index;
 /// predict the number of runs required in your `RleVec`, it is recommended to use
 /// `RleVec::with_capacity` whenever possible to specify how many runs the `RleVec` is expected
 /// to store.
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct RleVec<T> {
-    runs: Vec<InternalRun<T>>,
+///
+/// # Memory layout
+///
+/// The runs are stored as two vectors, one with the end positions of the runs and one with their
+/// values. Finding the run of an index is a binary search over the ends only, which makes better
+/// use of the cache than searching through interleaved ends and values. Both vectors are
+/// available as slices from [`ends`](#method.ends) and [`values`](#method.values).
+///
+/// # Index type
+///
+/// Every run stores the position of its last element. By default this is a `usize`, the optional
+/// second type parameter can be any [`RleIndex`](trait.RleIndex.html) to reduce the memory used
+/// per run. Operations that increase the length panic if the length no longer fits the index
+/// type, the `try_` variants return an [`IndexOverflow`](struct.IndexOverflow.html) instead.
+///
+/// ```
+/// # use rle_vec::RleVec;
+/// let mut rle: RleVec<u8, u32> = vec![1, 1, 1, 2, 2, 3].into_iter().collect();
+/// rle.try_insert(0, 3).unwrap();
+///
+/// assert_eq!(rle.to_vec(), vec![3, 1, 1, 1, 2, 2, 3]);
+/// ```
+#[derive(Clone)]
+pub struct RleVec<T, I = usize> {
+    ends: Vec<I>,
+    values: Vec<T>,
 }
 
 /// Represent a run inside the `RleVec`, can be obtained from the [`runs`](struct.RleVec.html#method.runs). A run is a serie of the same value.
@@ -149,11 +224,12 @@ pub struct Run<T> {
     pub value: T,
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-struct InternalRun<T> {
-    end: usize,
-    value: T,
+#[inline]
+fn to_index<I: RleIndex>(position: usize) -> I {
+    match I::from_usize(position) {
+        Some(index) => index,
+        None => panic!("{}", IndexOverflow),
+    }
 }
 
 impl<T> RleVec<T> {
@@ -168,7 +244,7 @@ impl<T> RleVec<T> {
     /// let rle = RleVec::<i32>::new();
     /// ```
     pub fn new() -> RleVec<T> {
-        RleVec { runs: Vec::new() }
+        RleVec { ends: Vec::new(), values: Vec::new() }
     }
 
     /// Constructs a new empty `RleVec<T>` with capacity for the number of runs.
@@ -198,7 +274,28 @@ impl<T> RleVec<T> {
     /// rle.push(11);
     /// ```
     pub fn with_capacity(capacity: usize) -> RleVec<T> {
-        RleVec { runs: Vec::with_capacity(capacity) }
+        RleVec { ends: Vec::with_capacity(capacity), values: Vec::with_capacity(capacity) }
+    }
+}
+
+impl<T, I: RleIndex> RleVec<T, I> {
+    /// Reserves capacity for at least `additional` more runs.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::<i32, u32>::default();
+    /// rle.reserve(10);
+    /// assert!(rle.capacity() >= 10);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        self.ends.reserve(additional);
+        self.values.reserve(additional);
+    }
+
+    /// Returns the number of runs the rle_vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        cmp::min(self.ends.capacity(), self.values.capacity())
     }
 
     /// Returns the number of elements in the rle_vector.
@@ -214,8 +311,8 @@ impl<T> RleVec<T> {
     /// assert_eq!(rle.len(), 3);
     /// ```
     pub fn len(&self) -> usize {
-        match self.runs.last() {
-            Some(run) => run.end + 1,
+        match self.ends.last() {
+            Some(end) => end.to_usize() + 1,
             None => 0,
         }
     }
@@ -232,7 +329,7 @@ impl<T> RleVec<T> {
     /// assert!(!rle.is_empty());
     /// ```
     pub fn is_empty(&self) -> bool {
-        self.runs.is_empty()
+        self.ends.is_empty()
     }
 
     /// Clears the vector, removing all values.
@@ -248,7 +345,8 @@ impl<T> RleVec<T> {
     /// assert!(rle.is_empty());
     /// ```
     pub fn clear(&mut self) {
-        self.runs.clear()
+        self.ends.clear();
+        self.values.clear();
     }
 
     /// Returns the last value, or None if it is empty.
@@ -263,10 +361,7 @@ impl<T> RleVec<T> {
     /// assert_eq!(rle.last(), None);
     /// ```
     pub fn last(&self) -> Option<&T> {
-        match self.runs.last() {
-            Some(last) => Some(&last.value),
-            None => None,
-        }
+        self.values.last()
     }
 
     /// Returns the last run, or None if it is empty.
@@ -292,14 +387,14 @@ impl<T> RleVec<T> {
     /// assert_eq!(rle.last_run(), Some(Run{ len: 1, value: &3 }));
     /// ```
     pub fn last_run(&self) -> Option<Run<&T>> {
-        let previous_end = if self.runs.len() >= 2 {
-            self.runs[self.runs.len() - 2].end + 1
+        let previous_end = if self.runs_len() >= 2 {
+            self.end(self.runs_len() - 2) + 1
         } else { 0 };
 
-        match self.runs.last() {
-            Some(last) => Some(Run {
-                len: last.end + 1 - previous_end,
-                value: &last.value
+        match self.values.last() {
+            Some(value) => Some(Run {
+                len: self.len() - previous_end,
+                value,
             }),
             None => None,
         }
@@ -322,7 +417,7 @@ impl<T> RleVec<T> {
     /// assert_eq!(rle.runs_len(), 3);
     /// ```
     pub fn runs_len(&self) -> usize {
-        self.runs.len()
+        self.ends.len()
     }
 
     /// Returns the 0-based start coordinates of the runs
@@ -342,12 +437,131 @@ impl<T> RleVec<T> {
     /// ```
     pub fn starts(&self) -> Vec<usize> {
         if self.is_empty() { return Vec::new() }
-        once(0).chain(self.runs.iter().take(self.runs_len() - 1).map(|r| r.end + 1)).collect()
+        once(0).chain(self.ends[..self.runs_len() - 1].iter().map(|end| end.to_usize() + 1)).collect()
     }
 
     /// Returns the 0-based end coordinates of the runs
-    pub fn ends(&self) -> Vec<usize> {
-        self.runs.iter().map(|r| r.end).collect()
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let rle = RleVec::from(&[1, 1, 2, 2, 3][..]);
+    ///
+    /// assert_eq!(rle.ends(), &[1, 3, 4]);
+    /// ```
+    pub fn ends(&self) -> &[I] {
+        &self.ends
+    }
+
+    /// Returns the values of the runs
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let rle = RleVec::from(&[1, 1, 2, 2, 3][..]);
+    ///
+    /// assert_eq!(rle.values(), &[1, 2, 3]);
+    /// ```
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Constructs an `RleVec` from the 0-based end positions of its runs and their values.
+    ///
+    /// The buffers are adopted without copying after checking that they have the same length and
+    /// that the ends are strictly increasing. Adjacent runs with equal values are accepted, call
+    /// [`coalesce`](#method.coalesce) to merge them.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleVec, InvalidRuns};
+    /// let rle = RleVec::from_ends(vec![2usize, 3, 6], vec![0, 1, 0]).unwrap();
+    /// assert_eq!(rle.to_vec(), vec![0, 0, 0, 1, 0, 0, 0]);
+    ///
+    /// let error = RleVec::from_ends(vec![2u32, 2], vec![0, 1]).unwrap_err();
+    /// assert_eq!(error, InvalidRuns::UnorderedEnds { run: 1, end: 2, previous_end: 2 });
+    /// ```
+    pub fn from_ends(ends: Vec<I>, values: Vec<T>) -> Result<RleVec<T, I>, InvalidRuns> {
+        let rle = RleVec { ends, values };
+        rle.validate()?;
+        Ok(rle)
+    }
+
+    /// Constructs an `RleVec` from the lengths of its runs and their values.
+    ///
+    /// The values are adopted without copying. Returns an error if the number of lengths and
+    /// values differ, a length is zero or the total length overflows the index type.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleVec, InvalidRuns};
+    /// let rle: RleVec<_> = RleVec::from_run_lengths(&[3, 1, 3], vec![0, 1, 0]).unwrap();
+    /// assert_eq!(rle.ends(), &[2, 3, 6]);
+    ///
+    /// let error = RleVec::<u8, u16>::from_run_lengths(&[65_536, 1], vec![0, 1]).unwrap_err();
+    /// assert_eq!(error, InvalidRuns::IndexOverflow);
+    /// ```
+    pub fn from_run_lengths(lengths: &[usize], values: Vec<T>) -> Result<RleVec<T, I>, InvalidRuns> {
+        if lengths.len() != values.len() {
+            return Err(InvalidRuns::LengthMismatch { ends: lengths.len(), values: values.len() })
+        }
+        let mut ends = Vec::with_capacity(lengths.len());
+        let mut len = 0usize;
+        for (run, &length) in lengths.iter().enumerate() {
+            if length == 0 {
+                return Err(InvalidRuns::EmptyRun { run })
+            }
+            len = len.checked_add(length).ok_or(InvalidRuns::IndexOverflow)?;
+            ends.push(I::from_usize(len - 1).ok_or(InvalidRuns::IndexOverflow)?);
+        }
+        Ok(RleVec { ends, values })
+    }
+
+    /// Decomposes the `RleVec` into the 0-based end positions of its runs and their values.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let rle = RleVec::from(&[1, 1, 2, 2, 2][..]);
+    /// let (ends, values) = rle.into_raw_parts();
+    ///
+    /// assert_eq!(ends, vec![1, 4]);
+    /// assert_eq!(values, vec![1, 2]);
+    /// assert_eq!(RleVec::from_ends(ends, values).unwrap().len(), 5);
+    /// ```
+    pub fn into_raw_parts(self) -> (Vec<I>, Vec<T>) {
+        (self.ends, self.values)
+    }
+
+    /// Checks that there is a value for every run end, that every run ends after the previous
+    /// one and that the length fits in a `usize`. All methods of `RleVec` maintain this, it only
+    /// fails for vectors constructed from inconsistent raw parts.
+    ///
+    /// Adjacent runs with equal values are valid, use [`is_canonical`](#method.is_canonical) to
+    /// check for those.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let rle = RleVec::from(&[1, 1, 2][..]);
+    /// assert_eq!(rle.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidRuns> {
+        if self.ends.len() != self.values.len() {
+            return Err(InvalidRuns::LengthMismatch { ends: self.ends.len(), values: self.values.len() })
+        }
+        match self.ends.windows(2).position(|w| w[0] >= w[1]) {
+            Some(run) => Err(InvalidRuns::UnorderedEnds {
+                run: run + 1,
+                end: self.end(run + 1),
+                previous_end: self.end(run),
+            }),
+            // the length is one past the last end
+            None if self.ends.last().is_some_and(|&end| end.to_usize() == usize::MAX) => {
+                Err(InvalidRuns::IndexOverflow)
+            },
+            None => Ok(()),
+        }
     }
 
     /// Returns an iterator over values. Comparable to a `Vec` iterator.
@@ -369,12 +583,12 @@ impl<T> RleVec<T> {
     /// assert_eq!(iterator.next(), Some(&3));
     /// assert_eq!(iterator.next(), None);
     /// ```
-    pub fn iter(&self) -> Iter<T> {
+    pub fn iter(&self) -> Iter<'_, T, I> {
         Iter {
             rle: self,
             run_index: 0,
             index: 0,
-            run_index_back: self.runs.len().saturating_sub(1),
+            run_index_back: self.runs_len().saturating_sub(1),
             index_back: self.len(), // starts out of range
         }
     }
@@ -397,31 +611,110 @@ impl<T> RleVec<T> {
     /// assert_eq!(iterator.next(), Some(Run{ len: 1, value: &3 }));
     /// assert_eq!(iterator.next(), None);
     /// ```
-    pub fn runs(&self) -> Runs<T> {
+    pub fn runs(&self) -> Runs<'_, T, I> {
         Runs { rle: self, run_index: 0, last_end: 0 }
     }
 
+    /// Returns a cursor pointing at the given index.
+    ///
+    /// Moving the cursor searches from its current run, which is faster than indexing for
+    /// sequential access.
+    ///
+    /// # Panics
+    /// Panics if index is out of bounds.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let rle = RleVec::from(&[1, 1, 2, 2, 2, 3][..]);
+    ///
+    /// let mut cursor = rle.cursor(0);
+    /// let mut sum = 0;
+    /// for i in (0..rle.len()).step_by(2) {
+    ///     cursor.seek(i);
+    ///     sum += *cursor.value();
+    /// }
+    /// assert_eq!(sum, 1 + 2 + 2);
+    /// ```
+    pub fn cursor(&self, index: usize) -> Cursor<'_, T, I> {
+        Cursor::new(self, index)
+    }
+
+    /// Returns a cursor for editing the vector at the given index.
+    ///
+    /// The cursor can point at `len` to append values. Consecutive edits through the cursor only
+    /// shift the ends of the runs between them, the remaining ends are shifted once when the
+    /// cursor is dropped.
+    ///
+    /// # Panics
+    /// Panics if `index > len`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[1, 1, 1, 1, 1, 1][..]);
+    /// {
+    ///     let mut cursor = rle.cursor_mut(0);
+    ///     for i in 0..3 {
+    ///         cursor.seek(i * 2);
+    ///         cursor.insert(0);
+    ///     }
+    /// }
+    /// assert_eq!(rle.to_vec(), vec![0, 1, 0, 1, 0, 1, 1, 1, 1]);
+    /// ```
+    pub fn cursor_mut(&mut self, index: usize) -> CursorMut<'_, T, I> {
+        CursorMut::new(self, index)
+    }
+
     fn run_index(&self, index: usize) -> usize {
-        match self.runs.binary_search_by(|run| run.end.cmp(&index)) {
-            Ok(i) => i,
-            Err(i) if i < self.runs.len() => i,
-            _ => panic!("index out of bounds: the len is {} but the index is {}", self.len(), index)
+        if index >= self.len() {
+            panic!("index out of bounds: the len is {} but the index is {}", self.len(), index)
+        }
+        // any index below the length fits the index type
+        match self.ends.binary_search(&to_index(index)) {
+            Ok(i) | Err(i) => i,
         }
     }
 
     fn index_info(&self, index: usize) -> (usize, usize, usize) {
         match self.run_index(index) {
-            0 => (0, 0, self.runs[0].end),
-            index => (index, self.runs[index - 1].end + 1, self.runs[index].end),
+            0 => (0, 0, self.end(0)),
+            index => (index, self.end(index - 1) + 1, self.end(index)),
         }
     }
+
+    /// Returns `true` if the length can grow by `additional` elements.
+    fn fits(&self, additional: usize) -> bool {
+        self.len().checked_add(additional)
+            .is_some_and(|len| len == 0 || I::from_usize(len - 1).is_some())
+    }
+
+    #[inline]
+    fn end(&self, run: usize) -> usize {
+        self.ends[run].to_usize()
+    }
+
+    #[inline]
+    fn set_end(&mut self, run: usize, end: usize) {
+        self.ends[run] = to_index(end);
+    }
+
+    fn insert_run(&mut self, run: usize, end: usize, value: T) {
+        self.ends.insert(run, to_index(end));
+        self.values.insert(run, value);
+    }
+
+    fn remove_run(&mut self, run: usize) -> T {
+        self.ends.remove(run);
+        self.values.remove(run)
+    }
 }
 
-impl<T: Eq> RleVec<T> {
+impl<T: Eq, I: RleIndex> RleVec<T, I> {
     /// Appends an element to the back of this rle_vector.
     ///
     /// # Panics
-    /// Panics if the number of elements in the vector overflows a usize.
+    /// Panics if the number of elements in the vector overflows the index type.
     ///
     /// # Example
     /// ```
@@ -438,7 +731,7 @@ impl<T: Eq> RleVec<T> {
     /// Appends the same element n times to the back of this rle_vec.
     ///
     /// # Panics
-    /// Panics if the number of elements in the vector overflows a usize.
+    /// Panics if the number of elements in the vector overflows the index type.
     ///
     /// # Example
     /// ```
@@ -450,19 +743,188 @@ impl<T: Eq> RleVec<T> {
     /// assert_eq!(rle[9], 2);
     /// ```
     pub fn push_n(&mut self, n: usize, value: T) {
-        if n == 0 { return; }
+        if let Err(e) = self.try_push_n(n, value) {
+            panic!("{}", e)
+        }
+    }
 
-        let end = match self.runs.last_mut() {
-            Some(ref mut last) if last.value == value => return last.end += n,
-            Some(last) => last.end + n,
-            None => n - 1,
-        };
+    /// Appends an element to the back of this rle_vector, or returns an error if the length no
+    /// longer fits the index type.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleVec, IndexOverflow};
+    /// let mut rle = RleVec::<u8, u16>::default();
+    /// rle.push_n(65_535, 1);
+    ///
+    /// assert_eq!(rle.try_push(2), Ok(()));
+    /// assert_eq!(rle.try_push(2), Err(IndexOverflow));
+    /// assert_eq!(rle.len(), 65_536);
+    /// ```
+    #[inline]
+    pub fn try_push(&mut self, value: T) -> Result<(), IndexOverflow> {
+        self.try_push_n(1, value)
+    }
+
+    /// Appends the same element n times to the back of this rle_vec, or returns an error if the
+    /// length no longer fits the index type. The rle_vec is unchanged if an error is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleVec, IndexOverflow};
+    /// let mut rle = RleVec::<u8, u16>::default();
+    ///
+    /// assert_eq!(rle.try_push_n(65_537, 1), Err(IndexOverflow));
+    /// assert!(rle.is_empty());
+    /// assert_eq!(rle.try_push_n(65_536, 1), Ok(()));
+    /// ```
+    pub fn try_push_n(&mut self, n: usize, value: T) -> Result<(), IndexOverflow> {
+        if n == 0 { return Ok(()) }
+
+        let end = self.len().checked_add(n - 1)
+            .and_then(I::from_usize)
+            .ok_or(IndexOverflow)?;
+
+        match self.values.last() {
+            Some(last) if *last == value => *self.ends.last_mut().unwrap() = end,
+            _ => {
+                self.ends.push(end);
+                self.values.push(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a cursor over mutable references to the values of the runs.
+    ///
+    /// Runs that end up with equal values are merged when the returned `RunsMut` is dropped.
+    ///
+    /// `RunsMut` is a cursor with a `next_run` method rather than an `Iterator`: the merge on drop
+    /// rewrites the values, which is only sound while no `&mut T` handed out by it is alive. An
+    /// iterator's items would keep borrowing the vector after the `RunsMut` is gone, so each run
+    /// borrows the cursor instead and has to be dropped before the next one is requested.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[1, 1, 2, 3, 3, 4][..]);
+    /// {
+    ///     let mut runs = rle.runs_mut();
+    ///     while let Some(run) = runs.next_run() {
+    ///         *run.value /= 2;
+    ///     }
+    /// }
+    /// assert_eq!(rle.to_vec(), vec![0, 0, 1, 1, 1, 2]);
+    /// assert_eq!(rle.runs_len(), 3);
+    /// ```
+    pub fn runs_mut(&mut self) -> RunsMut<'_, T, I> {
+        RunsMut { rle: self, run_index: 0, last_end: 0 }
+    }
+
+    /// Modifies the value of the run with the given index and merges it with its neighbours if
+    /// they become equal.
+    ///
+    /// # Panics
+    /// Panics if the run index is out of bounds.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[1, 1, 2, 3, 3][..]);
+    /// rle.update_run(1, |value| *value = 3);
+    ///
+    /// assert_eq!(rle.to_vec(), vec![1, 1, 3, 3, 3]);
+    /// assert_eq!(rle.runs_len(), 2);
+    /// ```
+    pub fn update_run<F: FnOnce(&mut T)>(&mut self, run: usize, f: F) {
+        f(&mut self.values[run]);
 
-        self.runs.push(InternalRun { value, end });
+        if run + 1 < self.runs_len() && self.values[run + 1] == self.values[run] {
+            let end = self.end(run + 1);
+            self.remove_run(run + 1);
+            self.set_end(run, end);
+        }
+        if run > 0 && self.values[run - 1] == self.values[run] {
+            let end = self.end(run);
+            self.remove_run(run);
+            self.set_end(run - 1, end);
+        }
+    }
+
+    /// Returns `true` if no run is empty and no adjacent runs have equal values.
+    ///
+    /// All methods of `RleVec` keep the runs canonical, except
+    /// [`CursorMut::split_run_here`](struct.CursorMut.html#method.split_run_here) and the
+    /// construction from raw parts.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[1, 1, 1, 2][..]);
+    /// assert!(rle.is_canonical());
+    ///
+    /// rle.cursor_mut(1).split_run_here();
+    /// assert!(!rle.is_canonical());
+    /// ```
+    pub fn is_canonical(&self) -> bool {
+        self.validate().is_ok() && self.values.windows(2).all(|w| w[0] != w[1])
+    }
+
+    /// Merges adjacent runs with equal values and removes empty runs.
+    ///
+    /// Runs that do not end after the previous run are treated as empty.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleVec;
+    /// let mut rle = RleVec::from(&[1, 1, 1, 2][..]);
+    /// rle.cursor_mut(1).split_run_here();
+    /// assert_eq!(rle.runs_len(), 3);
+    ///
+    /// rle.coalesce();
+    /// assert_eq!(rle.runs_len(), 2);
+    /// assert!(rle.is_canonical());
+    /// ```
+    pub fn coalesce(&mut self) {
+        let runs = cmp::min(self.ends.len(), self.values.len());
+        self.ends.truncate(runs);
+        self.values.truncate(runs);
+        if let Some(first) = (1..runs).find(|&run| !self.is_canonical_pair(run)) {
+            self.coalesce_from(first);
+        }
+    }
+
+    fn is_canonical_pair(&self, run: usize) -> bool {
+        self.ends[run - 1] < self.ends[run] && self.values[run - 1] != self.values[run]
+    }
+
+    /// Merges adjacent equal runs among the runs up to `to` and their successor.
+    fn merge_equal_runs(&mut self, to: usize) {
+        let to = cmp::min(to + 1, self.runs_len().saturating_sub(1));
+        if let Some(first) = (1..=to).find(|&run| !self.is_canonical_pair(run)) {
+            self.coalesce_from(first);
+        }
+    }
+
+    fn coalesce_from(&mut self, first: usize) {
+        let mut last = first - 1;
+        for run in first..self.ends.len() {
+            if self.ends[run] <= self.ends[last] {
+                // empty run
+            } else if self.values[run] == self.values[last] {
+                self.ends[last] = self.ends[run];
+            } else {
+                last += 1;
+                self.ends.swap(last, run);
+                self.values.swap(last, run);
+            }
+        }
+        self.ends.truncate(last + 1);
+        self.values.truncate(last + 1);
     }
 }
 
-impl<T: Clone> RleVec<T> {
+impl<T: Clone, I: RleIndex> RleVec<T, I> {
     /// Construct a `Vec<T>` from this `RleVec`.
     ///
     /// The values of the `RleVec` are cloned to produce the final `Vec`.
@@ -480,16 +942,16 @@ impl<T: Clone> RleVec<T> {
     pub fn to_vec(&self) -> Vec<T> {
         let mut res = Vec::with_capacity(self.len());
         let mut p = 0;
-        for r in &self.runs {
-            let n = r.end - p + 1;
-            res.extend(repeat(r.value.clone()).take(n));
+        for (end, value) in self.ends.iter().zip(&self.values) {
+            let n = end.to_usize() - p + 1;
+            res.extend(repeat_n(value.clone(), n));
             p += n;
         }
         res
     }
 }
 
-impl<T: Eq + Clone> RleVec<T> {
+impl<T: Eq + Clone, I: RleIndex> RleVec<T, I> {
     /// Modify the value at given index.
     ///
     /// This can result in the breaking of a run and therefore be an expensive operation.
@@ -514,23 +976,23 @@ impl<T: Eq + Clone> RleVec<T> {
     pub fn set(&mut self, index: usize, value: T) {
         let (mut p, start, end) = self.index_info(index);
 
-        if self.runs[p].value == value { return }
+        if self.values[p] == value { return }
 
         // a size 1 run is replaced with the new value or joined with next or previous
         if end - start == 0 {
             // can we join the previous run?
-            if p > 0 && self.runs[p - 1].value == value {
-                self.runs.remove(p);
-                self.runs[p - 1].end += 1;
+            if p > 0 && self.values[p - 1] == value {
+                self.remove_run(p);
+                self.set_end(p - 1, end);
                 p -= 1;
             }
             // can we join the next run?
-            if p < self.runs.len() - 1 && self.runs[p + 1].value == value {
-                self.runs.remove(p);
+            if p < self.runs_len() - 1 && self.values[p + 1] == value {
+                self.remove_run(p);
                 return;
             }
             // only one size-1 run in Rle replace its value
-            self.runs[p].value = value;
+            self.values[p] = value;
             return;
         }
 
@@ -538,31 +1000,31 @@ impl<T: Eq + Clone> RleVec<T> {
         if index == start {
             // compare to previous run
             if p > 0 {
-                if self.runs[p - 1].value == value {
-                    self.runs[p - 1].end += 1;
+                if self.values[p - 1] == value {
+                    self.set_end(p - 1, start);
                 } else {
-                    self.runs.insert(p, InternalRun { value, end: start });
+                    self.insert_run(p, start, value);
                 }
             } else {
-                self.runs.insert(0, InternalRun { value, end: 0 });
+                self.insert_run(0, 0, value);
             }
         } else if index == end {
             // decrease current run length
-            self.runs[p].end -= 1;
+            self.set_end(p, end - 1);
 
             // compare to next run
-            if p < self.runs.len() - 1 && self.runs[p + 1].value == value {
+            if p < self.runs_len() - 1 && self.values[p + 1] == value {
             } else {
-                self.runs.insert(p + 1, InternalRun { value, end });
+                self.insert_run(p + 1, end, value);
             }
         } else {
             // split current run
-            self.runs[p].end = index - 1;
-            let v = self.runs[p].value.clone();
+            self.set_end(p, index - 1);
+            let v = self.values[p].clone();
             // this might be more efficient using split_off, push and extend?
             // this implementation has complexity O((log n) + 2n)
-            self.runs.insert(p + 1, InternalRun { value, end: index });
-            self.runs.insert(p + 2, InternalRun { value: v, end });
+            self.insert_run(p + 1, index, value);
+            self.insert_run(p + 2, end, v);
         }
     }
 
@@ -583,22 +1045,27 @@ impl<T: Eq + Clone> RleVec<T> {
     pub fn remove(&mut self, index: usize) -> T {
         let (p, start, end) = self.index_info(index);
 
-        for run in self.runs[p..].iter_mut() {
-            run.end -= 1;
-        }
-
         // if size of the run is 1
         if end - start == 0 {
-            let InternalRun { value, .. } = self.runs.remove(p); // `p + 1` become p
+            let value = self.remove_run(p); // `p + 1` become p
+            self.decrement_ends(p);
             // if value before and after are equal
-            if p > 0 && self.runs_len() > 2 && self.runs[p - 1].value == self.runs[p].value {
-                let after_end = self.runs[p].end;
-                self.runs[p - 1].end = after_end;
-                self.runs.remove(p);
+            if p > 0 && p < self.runs_len() && self.values[p - 1] == self.values[p] {
+                self.ends[p - 1] = self.ends[p];
+                self.remove_run(p);
             }
             value
         }
-        else { self.runs[p].value.clone() }
+        else {
+            self.decrement_ends(p);
+            self.values[p].clone()
+        }
+    }
+
+    fn decrement_ends(&mut self, from_run: usize) {
+        for end in self.ends[from_run..].iter_mut() {
+            *end = to_index(end.to_usize() - 1);
+        }
     }
 
     /// Insert a value at the given index.
@@ -606,6 +1073,9 @@ impl<T: Eq + Clone> RleVec<T> {
     /// Because the positions of the values after the inserted value need to be changed,
     /// the complexity of this function is **O((log n) + 2n)**.
     ///
+    /// # Panics
+    /// Panics if `index > len` or if the number of elements overflows the index type.
+    ///
     /// # Example
     /// ```
     /// # use rle_vec::RleVec;
@@ -619,47 +1089,75 @@ impl<T: Eq + Clone> RleVec<T> {
     /// assert_eq!(rle.runs_len(), 5);
     /// ```
     pub fn insert(&mut self, index: usize, value: T) {
+        if let Err(e) = self.try_insert(index, value) {
+            panic!("{}", e)
+        }
+    }
+
+    /// Insert a value at the given index, or returns an error if the length no longer fits the
+    /// index type. The rle_vec is unchanged if an error is returned.
+    ///
+    /// # Panics
+    /// Panics if `index > len`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleVec, IndexOverflow};
+    /// let mut rle = RleVec::<u8, u16>::default();
+    /// rle.push_n(65_535, 1);
+    ///
+    /// assert_eq!(rle.try_insert(0, 2), Ok(()));
+    /// assert_eq!(rle.try_insert(0, 2), Err(IndexOverflow));
+    /// assert_eq!(rle[0], 2);
+    /// ```
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), IndexOverflow> {
         if index == self.len() {
-            return self.push(value);
+            return self.try_push(value);
         }
 
         let (p, start, end) = self.index_info(index);
+        // the last end increases to the current length
+        if I::from_usize(self.len()).is_none() {
+            return Err(IndexOverflow);
+        }
+
         // increment all run ends from position p
-        for run in self.runs[p..].iter_mut() {
-            run.end += 1;
+        for end in self.ends[p..].iter_mut() {
+            *end = to_index(end.to_usize() + 1);
         }
 
-        if self.runs[p].value == value { return }
+        if self.values[p] == value { return Ok(()) }
 
         // inserting value can split current run or maybe merge with previous or next
         if index == start {
             // compare to previous run
-            if p > 0 && self.runs[p - 1].value == value {
-                self.runs[p - 1].end += 1;
+            if p > 0 && self.values[p - 1] == value {
+                self.set_end(p - 1, index);
             } else {
-                self.runs.insert(p, InternalRun { value, end: index });
+                self.insert_run(p, index, value);
             }
         } else {
             // split current run
-            self.runs[p].end = index - 1;
-            self.runs.insert(p + 1, InternalRun { value, end: index });
-            let value = self.runs[p].value.clone();
-            self.runs.insert(p + 2, InternalRun { value, end: end + 1 });
+            self.set_end(p, index - 1);
+            self.insert_run(p + 1, index, value);
+            let value = self.values[p].clone();
+            self.insert_run(p + 2, end + 1, value);
         }
+        Ok(())
     }
 }
 
-impl<T> Index<usize> for RleVec<T> {
+impl<T, I: RleIndex> Index<usize> for RleVec<T, I> {
     type Output = T;
 
     fn index(&self, index: usize) -> &T {
-        &self.runs[self.run_index(index)].value
+        &self.values[self.run_index(index)]
     }
 }
 
-impl<T: Clone> Into<Vec<T>> for RleVec<T> {
-    fn into(self) -> Vec<T> {
-        self.to_vec()
+impl<T: Clone, I: RleIndex> From<RleVec<T, I>> for Vec<T> {
+    fn from(rle: RleVec<T, I>) -> Vec<T> {
+        rle.to_vec()
     }
 }
 
@@ -669,112 +1167,281 @@ impl<'a, T: Eq + Clone> From<&'a [T]> for RleVec<T> {
             return RleVec::new()
         }
 
-        let mut runs = Vec::new();
+        let mut ends = Vec::new();
+        let mut values = Vec::new();
         let mut last_value = slice[0].clone();
         for (i, v) in slice[1..].iter().enumerate() {
             if *v != last_value {
-                runs.push(InternalRun{
-                    end: i,
-                    value: last_value,
-                });
+                ends.push(i);
+                values.push(last_value);
                 last_value = v.clone();
             }
         }
 
-        runs.push(InternalRun{
-            end: slice.len() - 1,
-            value: last_value,
-        });
+        ends.push(slice.len() - 1);
+        values.push(last_value);
 
-        RleVec { runs }
+        RleVec { ends, values }
     }
 }
 
-impl<T: Eq> FromIterator<T> for RleVec<T> {
-    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=T> {
-        let mut rle = RleVec::new();
+impl<T: Eq, I: RleIndex> FromIterator<T> for RleVec<T, I> {
+    fn from_iter<It>(iter: It) -> Self where It: IntoIterator<Item=T> {
+        let mut rle = RleVec::default();
         rle.extend(iter);
         rle
     }
 }
 
-impl<T: Eq> FromIterator<Run<T>> for RleVec<T> {
-    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=Run<T>> {
+impl<T: Eq, I: RleIndex> FromIterator<Run<T>> for RleVec<T, I> {
+    fn from_iter<It>(iter: It) -> Self where It: IntoIterator<Item=Run<T>> {
         let iter = iter.into_iter();
         let (lower, _) = iter.size_hint();
 
-        let mut rle = RleVec::with_capacity(lower);
+        let mut rle = RleVec::default();
+        rle.reserve(lower);
         rle.extend(iter);
         rle
     }
 }
 
-impl<T> Default for RleVec<T> {
+impl<T, I> Default for RleVec<T, I> {
     fn default() -> Self {
-        RleVec::new()
+        RleVec { ends: Vec::new(), values: Vec::new() }
+    }
+}
+
+// the run ends and values with adjacent equal runs merged, so the comparisons and the hash do
+// not depend on runs split by CursorMut::split_run_here
+fn merged_runs<T: PartialEq, I>(rle: &RleVec<T, I>) -> impl Iterator<Item = (&I, &T)> {
+    let values = &rle.values;
+    rle.ends.iter().zip(values).enumerate()
+        .filter(move |&(run, _)| values.get(run + 1) != Some(&values[run]))
+        .map(|(_, run)| run)
+}
+
+impl<T: PartialEq, I: PartialEq> PartialEq for RleVec<T, I> {
+    fn eq(&self, other: &Self) -> bool {
+        merged_runs(self).eq(merged_runs(other))
+    }
+}
+
+impl<T: Eq, I: Eq> Eq for RleVec<T, I> {}
+
+// the vectors are ordered by their runs as (end, value) pairs, like the former vector of runs
+impl<T: PartialOrd, I: PartialOrd> PartialOrd for RleVec<T, I> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        merged_runs(self).partial_cmp(merged_runs(other))
     }
 }
 
-impl<T: Eq> Extend<T> for RleVec<T> {
-    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=T> {
+impl<T: Ord, I: Ord> Ord for RleVec<T, I> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        merged_runs(self).cmp(merged_runs(other))
+    }
+}
+
+impl<T: Hash + PartialEq, I: Hash> Hash for RleVec<T, I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(merged_runs(self).count());
+        for run in merged_runs(self) {
+            run.hash(state);
+        }
+    }
+}
+
+impl<T: Eq, I: RleIndex> Extend<T> for RleVec<T, I> {
+    fn extend<It>(&mut self, iter: It) where It: IntoIterator<Item=T> {
         let mut iter = iter.into_iter();
         if let Some(next_value) = iter.next() {
             // In order te possibly longer use the last run for extending the run-end we do not use the
             // push function to add values. This gives higher performance to extending the RleVec
-            // with data consisting of large runs.
-            let (pop, end) = if let Some(last_run) = self.runs.last() {
-                if last_run.value == next_value {
-                    (true, last_run.end + 1)
+            // with data consisting of large runs. The ends are only converted to the index type
+            // when a run is stored.
+            let (pop, mut end) = if let Some(last_value) = self.values.last() {
+                if *last_value == next_value {
+                    (true, self.len())
                 } else {
-                    (false, last_run.end + 1)
+                    (false, self.len())
                 }
             } else {
                 (false, 0)
             };
 
-            let mut rle_last = if pop {
-                let mut run = self.runs.pop().unwrap();
-                run.end = end;
-                run
+            let mut last_value = if pop {
+                self.ends.pop();
+                self.values.pop().unwrap()
             } else {
-                InternalRun { value: next_value, end }
+                next_value
             };
 
             for value in iter {
-                if value != rle_last.value {
-                    let next_end = rle_last.end;
-                    self.runs.push(rle_last);
-                    rle_last = InternalRun { value, end: next_end };
+                if value != last_value {
+                    self.ends.push(to_index(end));
+                    self.values.push(last_value);
+                    last_value = value;
                 }
-                rle_last.end += 1;
+                end += 1;
             }
-            self.runs.push(rle_last);
+            self.ends.push(to_index(end));
+            self.values.push(last_value);
         }
     }
 }
 
-impl<T: Eq> Extend<Run<T>> for RleVec<T> {
-    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=Run<T>> {
+impl<T: Eq, I: RleIndex> Extend<Run<T>> for RleVec<T, I> {
+    fn extend<It>(&mut self, iter: It) where It: IntoIterator<Item=Run<T>> {
         for Run{ len, value } in iter {
             self.push_n(len, value)
         }
     }
 }
 
-impl io::Write for RleVec<u8> {
+/// Pushes the runs of `rle` cut to the positions in `range` to `out`.
+pub(crate) fn push_slice<T: Eq + Clone, I: RleIndex>(out: &mut RleVec<T>, rle: &RleVec<T, I>, range: ops::Range<usize>) {
+    let mut start = 0;
+    for Run { len, value } in rle.runs() {
+        let end = start + len;
+        if end > range.start && start < range.end {
+            out.push_n(cmp::min(end, range.end) - cmp::max(start, range.start), value.clone());
+        }
+        start = end;
+    }
+}
+
+/// Assigns `value`, or no value, to the keys from `range.0` to `range.1` of the sorted and
+/// parallel `starts`, `ends` and `values`, cutting the ranges it overlaps.
+///
+/// The ends follow the convention of the caller: `successor(end)` is the first key after a range
+/// and `predecessor(start)` the end of a range right before `start`. A range touches the next one
+/// if the successor of its end is the next start, touching ranges with equal values are merged.
+pub(crate) fn splice_ranges<K: Copy + Ord, V: PartialEq + Clone>(starts: &mut Vec<K>, ends: &mut Vec<K>,
+    values: &mut Vec<V>, range: (K, K), value: Option<V>, successor: impl Fn(K) -> K,
+    predecessor: impl Fn(K) -> K)
+{
+    let (start, end) = range;
+
+    // the ranges that overlap the range or end right before or start right after it
+    let first = ends.partition_point(|&e| successor(e) < start);
+    let after = starts.partition_point(|&s| s <= successor(end));
+
+    let mut pieces = Vec::with_capacity(3);
+    if first < after && starts[first] < start {
+        pieces.push((starts[first], predecessor(start), values[first].clone()));
+    }
+    if let Some(value) = value {
+        pieces.push((start, end, value));
+    }
+    if first < after && ends[after - 1] > end {
+        pieces.push((successor(end), ends[after - 1], values[after - 1].clone()));
+    }
+
+    // the pieces only touch each other, the equal ones are merged
+    let mut merged: Vec<(K, K, V)> = Vec::with_capacity(pieces.len());
+    for piece in pieces {
+        match merged.last_mut() {
+            Some(previous) if successor(previous.1) == piece.0 && previous.2 == piece.2 => previous.1 = piece.1,
+            _ => merged.push(piece),
+        }
+    }
+
+    starts.splice(first..after, merged.iter().map(|piece| piece.0));
+    ends.splice(first..after, merged.iter().map(|piece| piece.1));
+    values.splice(first..after, merged.into_iter().map(|piece| piece.2));
+}
+
+/// Returns the number of leading samples of `size` bytes that are equal to the first one.
+///
+/// Compares eight bytes at a time against the first sample repeated, `size` has to divide 8.
+pub(crate) fn leading_run(bytes: &[u8], size: usize) -> usize {
+    let mut pattern = [0; 8];
+    for chunk in pattern.chunks_mut(size) {
+        chunk.copy_from_slice(&bytes[..size]);
+    }
+    let pattern = u64::from_le_bytes(pattern);
+
+    let mut words = bytes.chunks_exact(8);
+    let mut equal = 0;
+    for word in &mut words {
+        let diff = u64::from_le_bytes(<[u8; 8]>::try_from(word).unwrap()) ^ pattern;
+        if diff != 0 {
+            return (equal + diff.trailing_zeros() as usize / 8) / size
+        }
+        equal += 8;
+    }
+    let rest = words.remainder();
+    let rest_equal = rest.chunks_exact(size).take_while(|sample| *sample == &bytes[..size]).count();
+    equal / size + rest_equal
+}
+
+impl<I: RleIndex> io::Write for RleVec<u8, I> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.extend(buf.iter().cloned());
+        self.write_all(buf)?;
         Ok(buf.len())
     }
 
-    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.extend(buf.iter().cloned());
+    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        if !self.fits(buf.len()) {
+            return Err(io::Error::other(IndexOverflow));
+        }
+        while !buf.is_empty() {
+            let n = leading_run(buf, 1);
+            self.push_n(n, buf[0]);
+            buf = &buf[n..];
+        }
         Ok( () )
     }
 
     fn flush(&mut self) -> io::Result<()> { Ok( () ) }
 }
 
+macro_rules! impl_write_runs {
+    ($($t:ty),*) => {
+        $(
+            impl<I: RleIndex> RleVec<$t, I> {
+                #[doc = concat!("Appends the little-endian `", stringify!($t), "` samples in `bytes`.")]
+                ///
+                /// The run boundaries are found by comparing eight bytes at a time, which makes
+                /// appending long runs of equal samples much faster than pushing them one by one.
+                /// Returns an `InvalidInput` error if the length of `bytes` is not a multiple of
+                /// the sample size, or an error wrapping `IndexOverflow` if the length no longer
+                /// fits the index type. The vector is unchanged if an error is returned.
+                ///
+                /// # Example
+                /// ```
+                /// # use rle_vec::RleVec;
+                #[doc = concat!("let mut rle: RleVec<", stringify!($t), "> = RleVec::new();")]
+                #[doc = concat!("let samples: Vec<u8> = [7, 7, 7, 9].iter().flat_map(|s: &", stringify!($t), "| s.to_le_bytes()).collect();")]
+                /// rle.write_runs(&samples).unwrap();
+                ///
+                /// assert_eq!(rle.to_vec(), vec![7, 7, 7, 9]);
+                /// assert!(rle.write_runs(&[1]).is_err());
+                /// ```
+                pub fn write_runs(&mut self, mut bytes: &[u8]) -> io::Result<()> {
+                    const SIZE: usize = mem::size_of::<$t>();
+                    if bytes.len() % SIZE != 0 {
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                            concat!("the input is not a whole number of ", stringify!($t), " samples")));
+                    }
+                    if !self.fits(bytes.len() / SIZE) {
+                        return Err(io::Error::other(IndexOverflow));
+                    }
+                    while !bytes.is_empty() {
+                        let n = leading_run(bytes, SIZE);
+                        let mut sample = [0; SIZE];
+                        sample.copy_from_slice(&bytes[..SIZE]);
+                        self.push_n(n, <$t>::from_le_bytes(sample));
+                        bytes = &bytes[n * SIZE..];
+                    }
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+impl_write_runs!(u16, u32);
+
 /// Immutable `RelVec` iterator over references of values.
 ///
 /// Can be obtained from the [`iter`](struct.RleVec.html#method.iter) or the `into_iter` methods.
@@ -794,42 +1461,42 @@ impl io::Write for RleVec<u8> {
 /// assert_eq!(iterator.next(), Some(&3));
 /// assert_eq!(iterator.next(), None);
 /// ```
-pub struct Iter<'a, T: 'a> {
-    rle: &'a RleVec<T>,
+pub struct Iter<'a, T: 'a, I: 'a = usize> {
+    rle: &'a RleVec<T, I>,
     run_index: usize,
     index: usize,
     index_back: usize,
     run_index_back: usize,
 }
 
-impl<'a, T: 'a> IntoIterator for &'a RleVec<T> {
+impl<'a, T: 'a, I: RleIndex> IntoIterator for &'a RleVec<T, I> {
     type Item = &'a T;
-    type IntoIter = Iter<'a, T>;
+    type IntoIter = Iter<'a, T, I>;
 
     fn into_iter(self) -> Self::IntoIter {
         Iter {
             rle: self,
             run_index: 0,
             index: 0,
-            run_index_back: self.runs.len().saturating_sub(1),
+            run_index_back: self.runs_len().saturating_sub(1),
             index_back: self.len(), // starts out of range
         }
     }
 }
 
-impl<'a, T: 'a> Iterator for Iter<'a, T> {
+impl<'a, T: 'a, I: RleIndex> Iterator for Iter<'a, T, I> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.index == self.index_back {
             return None
         }
-        let run = &self.rle.runs[self.run_index];
+        let value = &self.rle.values[self.run_index];
         self.index += 1;
-        if self.index > run.end {
+        if self.index > self.rle.end(self.run_index) {
             self.run_index += 1;
         }
-        Some(&run.value)
+        Some(value)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -854,24 +1521,24 @@ impl<'a, T: 'a> Iterator for Iter<'a, T> {
         self.run_index = if self.index < self.rle.len() {
             self.rle.run_index(self.index)
         } else {
-            self.rle.runs.len() - 1
+            self.rle.runs_len() - 1
         };
         self.next()
     }
 }
 
-impl<'a, T: 'a> ExactSizeIterator for Iter<'a, T> { }
+impl<'a, T: 'a, I: RleIndex> ExactSizeIterator for Iter<'a, T, I> { }
 
-impl<'a, T: 'a> DoubleEndedIterator for Iter<'a, T> {
+impl<'a, T: 'a, I: RleIndex> DoubleEndedIterator for Iter<'a, T, I> {
     fn next_back(&mut self) -> Option<Self::Item> {
         if self.index_back == self.index {
             return None
         }
         self.index_back -= 1;
-        if self.run_index_back > 0 && self.index_back <= self.rle.runs[self.run_index_back - 1].end {
+        if self.run_index_back > 0 && self.index_back <= self.rle.end(self.run_index_back - 1) {
             self.run_index_back -= 1;
         }
-        Some(&self.rle.runs[self.run_index_back].value)
+        Some(&self.rle.values[self.run_index_back])
     }
 }
 
@@ -892,20 +1559,21 @@ impl<'a, T: 'a> DoubleEndedIterator for Iter<'a, T> {
 /// assert_eq!(iterator.next(), Some(Run{ len: 1, value: &3 }));
 /// assert_eq!(iterator.next(), None);
 /// ```
-pub struct Runs<'a, T:'a> {
-    rle: &'a RleVec<T>,
+pub struct Runs<'a, T: 'a, I: 'a = usize> {
+    rle: &'a RleVec<T, I>,
     run_index: usize,
     last_end: usize,
 }
 
-impl<'a, T: 'a> Iterator for Runs<'a, T> {
+impl<'a, T: 'a, I: RleIndex> Iterator for Runs<'a, T, I> {
     type Item = Run<&'a T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.run_index == self.rle.runs.len() {
+        if self.run_index == self.rle.runs_len() {
             return None
         }
-        let &InternalRun { ref value, end } = self.rle.runs.index(self.run_index);
+        let value = &self.rle.values[self.run_index];
+        let end = self.rle.end(self.run_index);
         let len = end - self.last_end + 1;
         self.run_index += 1;
         self.last_end = end + 1;
@@ -913,7 +1581,7 @@ impl<'a, T: 'a> Iterator for Runs<'a, T> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.rle.runs.len() - self.run_index;
+        let len = self.rle.runs_len() - self.run_index;
         (len, Some(len))
     }
 
@@ -923,26 +1591,77 @@ impl<'a, T: 'a> Iterator for Runs<'a, T> {
     }
 
     fn last(self) -> Option<Self::Item> {
-        if self.run_index == self.rle.runs.len() {
+        if self.run_index == self.rle.runs_len() {
             return None
         }
         self.rle.last_run()
     }
 
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.run_index = cmp::min(self.run_index + n, self.rle.runs.len());
+        self.run_index = cmp::min(self.run_index + n, self.rle.runs_len());
         self.last_end = if self.run_index != 0 {
-            self.rle.runs[self.run_index - 1].end + 1
+            self.rle.end(self.run_index - 1) + 1
         } else { 0 };
         self.next()
     }
 }
 
-impl<'a, T: 'a> ExactSizeIterator for Runs<'a, T> { }
+impl<'a, T: 'a, I: RleIndex> ExactSizeIterator for Runs<'a, T, I> { }
+
+/// Cursor over the runs of an `RleVec` that gives mutable access to their values.
+///
+/// Each run is returned as a `Run<&mut T>` that borrows the cursor, so it has to be dropped before
+/// the next run is requested. When the `RunsMut` is dropped the runs that were visited are
+/// merged with their neighbours if their values became equal.
+///
+/// Can be obtained from the [`runs_mut`](struct.RleVec.html#method.runs_mut) method.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, Run};
+/// let mut rle = RleVec::from(&[1, 1, 2, 3][..]);
+/// {
+///     let mut runs = rle.runs_mut();
+///     let run = runs.next_run().unwrap();
+///     assert_eq!(run.len, 2);
+///     *run.value = 2;
+/// }
+/// assert_eq!(rle.runs().next(), Some(Run { len: 3, value: &2 }));
+/// ```
+pub struct RunsMut<'a, T: Eq + 'a, I: RleIndex + 'a = usize> {
+    rle: &'a mut RleVec<T, I>,
+    run_index: usize,
+    last_end: usize,
+}
+
+impl<'a, T: Eq + 'a, I: RleIndex> RunsMut<'a, T, I> {
+    /// Returns the next run, or `None` if all runs were visited.
+    pub fn next_run(&mut self) -> Option<Run<&mut T>> {
+        if self.run_index == self.rle.runs_len() {
+            return None
+        }
+        let end = self.rle.end(self.run_index);
+        let len = end - self.last_end + 1;
+        self.last_end = end + 1;
+        self.run_index += 1;
+        Some(Run { len, value: &mut self.rle.values[self.run_index - 1] })
+    }
+}
+
+impl<'a, T: Eq + 'a, I: RleIndex> Drop for RunsMut<'a, T, I> {
+    fn drop(&mut self) {
+        if self.run_index > 0 {
+            self.rle.merge_equal_runs(self.run_index - 1);
+        }
+    }
+}
 
 #[cfg(test)]
+#[allow(clippy::iter_nth_zero, clippy::iter_skip_next, clippy::iter_skip_zero, clippy::clone_on_copy,
+        clippy::unused_io_amount, clippy::manual_repeat_n)]
 mod tests {
     use super::*;
+    use test_util::Rng;
 
     #[test]
     fn rare_usage() {
@@ -1023,7 +1742,7 @@ mod tests {
         assert_eq!(rle.last(), None);
         assert_eq!(rle.last_run(), None);
 
-        let mut rle = RleVec::default();
+        let mut rle: RleVec<_> = RleVec::default();
         rle.push(1);
         assert_eq!(rle.len(), 1);
     }
@@ -1114,6 +1833,23 @@ mod tests {
         assert_eq!(rle.to_vec(), vec![1, 1, 1, 1, 1, 1, 1, 4, 4, 3]);
     }
 
+    #[test]
+    fn remove_merges_neighbours_and_empties() {
+        // removing a single value run merges its neighbours
+        let mut rle = RleVec::from(&[0, 1, 0][..]);
+        assert_eq!(rle.remove(1), 1);
+        assert_eq!(rle.runs_len(), 1);
+
+        // removing the last run
+        let mut rle = RleVec::from(&[0, 1, 2, 3][..]);
+        assert_eq!(rle.remove(3), 3);
+        assert_eq!(rle.to_vec(), vec![0, 1, 2]);
+        assert_eq!(rle.remove(0), 0);
+        assert_eq!(rle.remove(0), 1);
+        assert_eq!(rle.remove(0), 2);
+        assert!(rle.is_empty());
+    }
+
     #[test]
     fn inserting_values() {
         let mut v = vec![0,0,0,1,1,1,1,1,1,1,3,3,1,0,99,99,9];
@@ -1294,18 +2030,152 @@ mod tests {
 
     }
 
+    #[test]
+    fn mutating_runs() {
+        let mut rle = RleVec::from(&[1, 1, 2, 2, 3, 4, 4, 5][..]);
+        {
+            let mut runs = rle.runs_mut();
+            let mut lens = Vec::new();
+            while let Some(run) = runs.next_run() {
+                lens.push(run.len);
+                *run.value = (*run.value + 1) / 2;
+            }
+            assert_eq!(lens, vec![2, 2, 1, 2, 1]);
+        }
+        assert_eq!(rle.to_vec(), vec![1, 1, 1, 1, 2, 2, 2, 3]);
+        assert_eq!(rle.ends(), &[3, 6, 7]);
+
+        // only the visited runs and their successor are merged
+        let mut rle = RleVec::from(&[0, 1, 2, 2, 1, 1][..]);
+        {
+            let mut runs = rle.runs_mut();
+            *runs.next_run().unwrap().value = 1;
+        }
+        assert_eq!(rle.ends(), &[1, 3, 5]);
+        rle.runs_mut();
+        assert_eq!(rle.runs_len(), 3);
+
+        rle.update_run(1, |v| *v = 1);
+        assert_eq!(rle.to_vec(), vec![1; 6]);
+        assert_eq!(rle.runs_len(), 1);
+        rle.update_run(0, |v| *v = 0);
+        assert_eq!(rle.ends(), &[5]);
+        assert_eq!(rle.values(), &[0]);
+    }
+
+    #[test]
+    fn canonical_runs() {
+        let mut rle = RleVec { ends: vec![1usize, 3, 3, 5, 8], values: vec![0, 0, 1, 1, 2] };
+        assert_eq!(rle.validate(), Err(InvalidRuns::UnorderedEnds { run: 2, end: 3, previous_end: 3 }));
+        assert!(!rle.is_canonical());
+
+        rle.coalesce();
+        assert_eq!(rle.validate(), Ok(()));
+        assert!(rle.is_canonical());
+        assert_eq!(rle.ends(), &[3, 5, 8]);
+        assert_eq!(rle.values(), &[0, 1, 2]);
+        assert_eq!(rle.to_vec(), vec![0, 0, 0, 0, 1, 1, 2, 2, 2]);
+
+        let mut rle = RleVec { ends: vec![2usize, 4], values: vec![1, 2, 3] };
+        assert_eq!(rle.validate(), Err(InvalidRuns::LengthMismatch { ends: 2, values: 3 }));
+        rle.coalesce();
+        assert_eq!(rle.to_vec(), vec![1, 1, 1, 2, 2]);
+
+        let mut rle: RleVec<u8> = RleVec::new();
+        rle.coalesce();
+        assert!(rle.is_canonical());
+    }
+
+    #[test]
+    fn raw_parts() {
+        let rle = RleVec::from(&[0, 0, 1, 2, 2, 2][..]);
+        let lengths: Vec<_> = rle.runs().map(|run| run.len).collect();
+        let (ends, values) = rle.clone().into_raw_parts();
+
+        assert_eq!(RleVec::from_run_lengths(&lengths, values.clone()), Ok(rle.clone()));
+        assert_eq!(RleVec::from_ends(ends, values), Ok(rle));
+
+        let empty = RleVec::<u8>::from_run_lengths(&[], vec![]).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(RleVec::<u8>::from_run_lengths(&[1, 0, 1], vec![0, 1, 2]), Err(InvalidRuns::EmptyRun { run: 1 }));
+        assert_eq!(RleVec::<u8>::from_run_lengths(&[1], vec![0, 1]), Err(InvalidRuns::LengthMismatch { ends: 1, values: 2 }));
+        assert_eq!(RleVec::<u8>::from_run_lengths(&[usize::MAX, 2], vec![0, 1]), Err(InvalidRuns::IndexOverflow));
+        assert_eq!(RleVec::<u8>::from_ends(vec![3], vec![]), Err(InvalidRuns::LengthMismatch { ends: 1, values: 0 }));
+        assert_eq!(RleVec::from_ends(vec![3, usize::MAX], vec![0, 1]), Err(InvalidRuns::IndexOverflow));
+        assert_eq!(RleVec::from_ends(vec![3, usize::MAX - 1], vec![0, 1]).map(|rle| rle.len()), Ok(usize::MAX));
+    }
+
     #[test]
     fn starts_ends() {
         let v = vec![0,0,0,1,1,1,1,1,1,1,3,3,1,0,99,99,9];
         let rle = v.iter().cloned().collect::<RleVec<_>>();
         assert_eq!(rle.starts(), vec![0,3,10,12,13,14,16]);
-        assert_eq!(rle.ends(),   vec![2,9,11,12,13,15,16]);
+        assert_eq!(rle.ends(),   &[2,9,11,12,13,15,16]);
+        assert_eq!(rle.values(), &[0,1,3,1,0,99,9]);
 
         let rle = RleVec::<i64>::new();
         assert!(rle.starts().is_empty());
         assert!(rle.ends().is_empty());
     }
 
+    #[test]
+    fn ordering() {
+        // the value of the first run decides before the later run ends
+        let a = RleVec::from(&[1, 9, 9, 9, 9, 9][..]);
+        let b = RleVec::from(&[2, 0, 0, 0, 5, 5][..]);
+        assert_eq!(a.cmp(&b), cmp::Ordering::Less);
+        assert_eq!(a.partial_cmp(&b), Some(cmp::Ordering::Less));
+
+        let a = RleVec::from(&[0, 0, 1][..]);
+        let b = RleVec::from(&[0, 0, 2][..]);
+        assert!(a < b);
+        assert!(RleVec::from(&[3, 3][..]) > RleVec::from(&[3][..]));
+        assert_eq!(a.cmp(&a.clone()), cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn compact_index() {
+        let v = vec![0,0,0,1,1,1,1,1,1,1,3,3,1,0,99,99,9];
+        let mut rle: RleVec<u8, u16> = v.iter().cloned().collect();
+        let mut reference = RleVec::from(&v[..]);
+        assert_eq!(rle.to_vec(), v);
+        assert_eq!(rle.starts(), reference.starts());
+
+        rle.set(4, 7);
+        reference.set(4, 7);
+        rle.insert(12, 3);
+        reference.insert(12, 3);
+        assert_eq!(rle.remove(0), reference.remove(0));
+        assert_eq!(rle.to_vec(), reference.to_vec());
+        assert_eq!(rle.runs().collect::<Vec<_>>(), reference.runs().collect::<Vec<_>>());
+
+        // the last end has to fit the index type
+        let mut rle = RleVec::<u8, u16>::default();
+        assert_eq!(rle.try_push_n(u16::MAX as usize, 0), Ok(()));
+        assert_eq!(rle.try_insert(10, 1), Ok(()));
+        assert_eq!(rle.len(), u16::MAX as usize + 1);
+        assert_eq!(rle.try_push(1), Err(IndexOverflow));
+        assert_eq!(rle.try_insert(0, 0), Err(IndexOverflow));
+        assert_eq!(rle.try_push_n(0, 1), Ok(()));
+        assert_eq!(rle.len(), u16::MAX as usize + 1);
+        assert_eq!(rle.runs_len(), 3);
+        assert_eq!(rle.last(), Some(&0));
+
+        rle.set(10, 0);
+        rle.remove(0);
+        assert_eq!(rle.try_push_n(2, 0), Err(IndexOverflow));
+        assert_eq!(rle.try_push_n(1, 0), Ok(()));
+        assert_eq!(rle.runs_len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows its index type")]
+    fn compact_index_overflow() {
+        let mut rle: RleVec<bool, u16> = RleVec::default();
+        rle.extend(std::iter::repeat_n(true, 1 << 16));
+        rle.push(false);
+    }
+
     #[test]
     fn write_trait() {
         use std::io::Write;
@@ -1322,5 +2192,49 @@ mod tests {
         rle.write(&[3,3,3]).unwrap();
         assert_eq!(rle.runs_len(),5);
         assert_eq!(rle.len(),19);
+
+        let mut rle = RleVec::<u8, u16>::default();
+        rle.write_all(&[0; 1 << 16]).unwrap();
+        assert!(rle.write_all(&[1]).is_err());
+        assert_eq!(rle.len(), 1 << 16);
+    }
+
+    #[test]
+    fn run_scanning() {
+        let mut rng = Rng::new(3);
+        let mut bytes = Vec::new();
+        for _ in 0..500 {
+            let len = rng.below(40);
+            bytes.extend(repeat_n(rng.below(3) as u8, len));
+        }
+
+        for &size in &[1, 2, 4] {
+            for start in (0..bytes.len() - 64).step_by(size * 7) {
+                let buf = &bytes[start..bytes.len() / size * size];
+                let first = &buf[..size];
+                let naive = buf.chunks(size).take_while(|sample| *sample == first).count();
+                assert_eq!(leading_run(buf, size), naive);
+            }
+        }
+
+        let mut rle: RleVec<_> = RleVec::new();
+        io::Write::write_all(&mut rle, &bytes).unwrap();
+        assert_eq!(rle, RleVec::from(&bytes[..]));
+
+        let even = &bytes[..bytes.len() / 4 * 4];
+        let mut rle16: RleVec<u16> = RleVec::new();
+        rle16.write_runs(even).unwrap();
+        let samples: Vec<u16> = even.chunks(2).map(|s| u16::from_le_bytes([s[0], s[1]])).collect();
+        assert_eq!(rle16.to_vec(), samples);
+        assert!(rle16.is_canonical());
+
+        let mut rle32: RleVec<u32, u16> = RleVec::default();
+        rle32.write_runs(even).unwrap();
+        let samples: Vec<u32> = even.chunks(4).map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]])).collect();
+        assert_eq!(rle32.to_vec(), samples);
+
+        assert_eq!(rle32.write_runs(&[0; 6]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(rle32.write_runs(&[0; 1 << 18]).is_err());
+        assert_eq!(rle32.len(), samples.len());
     }
 }
@@ -0,0 +1,476 @@
+//! An ordered map from non-overlapping ranges of keys to values.
+
+use std::cmp;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::ops::Range;
+
+use super::{splice_ranges, RleIndex, RleVec, Run};
+
+/// A key that can be advanced by a number of steps, like the unstable `std::iter::Step`.
+///
+/// Implemented for the primitive integer types.
+pub trait Step: Ord + Copy {
+    /// Returns the key `count` steps after `self`, or `None` if it overflows.
+    fn forward(self, count: usize) -> Option<Self>;
+}
+
+macro_rules! impl_step {
+    ($($t:ty),*) => {$(
+        impl Step for $t {
+            fn forward(self, count: usize) -> Option<Self> {
+                let count = <$t>::try_from(count).ok()?;
+                self.checked_add(count)
+            }
+        }
+    )*}
+}
+
+// a signed key can advance by more steps than its positive maximum
+macro_rules! impl_signed_step {
+    ($($t:ty => $u:ty),*) => {$(
+        impl Step for $t {
+            fn forward(self, count: usize) -> Option<Self> {
+                let count = <$u>::try_from(count).ok()?;
+                self.checked_add_unsigned(count)
+            }
+        }
+    )*}
+}
+
+impl_step!(u8, u16, u32, u64, u128, usize);
+impl_signed_step!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+/// An ordered map from non-overlapping ranges of keys to values.
+///
+/// Unlike an `RleVec`, which covers the positions from zero to its length, an `RleMap` assigns
+/// values to arbitrary ranges of keys like addresses or timestamps, keys outside of every range
+/// hold no value. Inserting a range overwrites the parts of the ranges it overlaps, and
+/// touching ranges with equal values are merged like the runs of an `RleVec`, so two maps
+/// holding the same assignments compare equal.
+///
+/// Looking up a key is a binary search over the ranges, inserting or removing a range
+/// replaces the ranges it overlaps, which is **O(ranges)** in the worst case.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleMap;
+/// let mut map = RleMap::new();
+/// map.insert_range(0x1000u64..0x2000, "text");
+/// map.insert_range(0x2000..0x2800, "text");
+/// map.insert_range(0x8000..0x9000, "data");
+///
+/// assert_eq!(map.get(0x1fff), Some(&"text"));
+/// assert_eq!(map.get(0x3000), None);
+/// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(0x1000..0x2800, &"text"), (0x8000..0x9000, &"data")]);
+/// assert_eq!(map.gaps(0..0x10000).collect::<Vec<_>>(), vec![0..0x1000, 0x2800..0x8000, 0x9000..0x10000]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RleMap<K, V> {
+    // the start and the exclusive end of every range
+    starts: Vec<K>,
+    ends: Vec<K>,
+    values: Vec<V>,
+}
+
+impl<K, V> RleMap<K, V> {
+    /// Constructs a new empty `RleMap`.
+    pub fn new() -> RleMap<K, V> {
+        RleMap { starts: Vec::new(), ends: Vec::new(), values: Vec::new() }
+    }
+
+    /// Returns the number of ranges.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the map contains no ranges.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Removes all ranges.
+    pub fn clear(&mut self) {
+        self.starts.clear();
+        self.ends.clear();
+        self.values.clear();
+    }
+
+    /// Returns an iterator over the ranges and their values.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { map: self, run: 0 }
+    }
+}
+
+impl<K: Step, V> RleMap<K, V> {
+    // the first range that ends after key
+    fn run_after(&self, key: K) -> usize {
+        self.ends.partition_point(|&end| end <= key)
+    }
+
+    /// Returns the value of `key`, or `None` if no range contains it.
+    pub fn get(&self, key: K) -> Option<&V> {
+        self.get_range(key).map(|(_, value)| value)
+    }
+
+    /// Returns the range containing `key` and its value.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleMap;
+    /// let mut map = RleMap::new();
+    /// map.insert_range(-10i64..10, 'a');
+    ///
+    /// assert_eq!(map.get_range(0), Some((-10..10, &'a')));
+    /// assert_eq!(map.get_range(10), None);
+    /// ```
+    pub fn get_range(&self, key: K) -> Option<(Range<K>, &V)> {
+        let run = self.run_after(key);
+        match self.starts.get(run) {
+            Some(&start) if start <= key => Some((start..self.ends[run], &self.values[run])),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if a range contains `key`.
+    pub fn contains_key(&self, key: K) -> bool {
+        self.get_range(key).is_some()
+    }
+
+    /// Returns an iterator over the ranges overlapping `range`, cut to fit in `range`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleMap;
+    /// let mut map = RleMap::new();
+    /// map.insert_range(0u32..10, 1);
+    /// map.insert_range(20..30, 2);
+    ///
+    /// assert_eq!(map.range(5..25).collect::<Vec<_>>(), vec![(5..10, &1), (20..25, &2)]);
+    /// ```
+    pub fn range(&self, range: Range<K>) -> RangeIter<'_, K, V> {
+        RangeIter { map: self, run: self.run_after(range.start), range }
+    }
+
+    /// Returns an iterator over the parts of `range` that no range contains.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleMap;
+    /// let mut map = RleMap::new();
+    /// map.insert_range(10u8..20, ());
+    ///
+    /// assert_eq!(map.gaps(0..15).collect::<Vec<_>>(), vec![0..10]);
+    /// assert_eq!(map.gaps(12..18).count(), 0);
+    /// ```
+    pub fn gaps(&self, range: Range<K>) -> Gaps<'_, K, V> {
+        Gaps { map: self, run: self.run_after(range.start), range }
+    }
+}
+
+impl<K: Step, V: Eq + Clone> RleMap<K, V> {
+    fn replace(&mut self, range: Range<K>, value: Option<V>) {
+        let (start, end) = (range.start, range.end);
+        if start >= end {
+            return
+        }
+
+        // the ends are exclusive, a range ending at a key touches the range starting at it
+        splice_ranges(&mut self.starts, &mut self.ends, &mut self.values, (start, end), value,
+                      |end| end, |start| start);
+    }
+
+    /// Assigns `value` to all keys in `range`, overwriting the values it overlaps.
+    ///
+    /// Touching ranges with equal values are merged. An empty range changes nothing.
+    pub fn insert_range(&mut self, range: Range<K>, value: V) {
+        self.replace(range, Some(value))
+    }
+
+    /// Assigns `value` to `key`.
+    ///
+    /// # Panics
+    /// Panics if `key` is the largest value of its type, which no range can contain.
+    pub fn insert(&mut self, key: K, value: V) {
+        let end = key.forward(1).expect("the key is the largest value of its type");
+        self.replace(key..end, Some(value))
+    }
+
+    /// Removes the values of all keys in `range`, cutting the ranges it overlaps.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleMap;
+    /// let mut map = RleMap::new();
+    /// map.insert_range(0u16..100, 'x');
+    /// map.remove_range(40..60);
+    ///
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(0..40, &'x'), (60..100, &'x')]);
+    /// ```
+    pub fn remove_range(&mut self, range: Range<K>) {
+        self.replace(range, None)
+    }
+
+    /// Constructs a map of the runs of `rle`, placing its first position at the key `start`.
+    ///
+    /// # Panics
+    /// Panics if a key overflows the key type.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleMap, RleVec};
+    /// let rle = RleVec::from(&[1, 1, 2][..]);
+    /// let map = RleMap::from_rle(1_000i64, &rle);
+    ///
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1_000..1_002, &1), (1_002..1_003, &2)]);
+    /// ```
+    pub fn from_rle<I: RleIndex>(start: K, rle: &RleVec<V, I>) -> RleMap<K, V> {
+        let mut map = RleMap::new();
+        let mut key = start;
+        for Run { len, value } in rle.runs() {
+            let end = key.forward(len).expect("the key overflows its type");
+            map.replace(key..end, Some(value.clone()));
+            key = end;
+        }
+        map
+    }
+}
+
+impl<K, V> Default for RleMap<K, V> {
+    fn default() -> Self {
+        RleMap::new()
+    }
+}
+
+impl<K: Step, V: Eq + Clone> FromIterator<(Range<K>, V)> for RleMap<K, V> {
+    fn from_iter<T>(iter: T) -> Self where T: IntoIterator<Item = (Range<K>, V)> {
+        let mut map = RleMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Step, V: Eq + Clone> Extend<(Range<K>, V)> for RleMap<K, V> {
+    fn extend<T>(&mut self, iter: T) where T: IntoIterator<Item = (Range<K>, V)> {
+        for (range, value) in iter {
+            self.insert_range(range, value);
+        }
+    }
+}
+
+impl<'a, K: Copy + 'a, V: 'a> IntoIterator for &'a RleMap<K, V> {
+    type Item = (Range<K>, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Immutable `RleMap` iterator over the ranges and their values.
+///
+/// Can be obtained from the [`iter`](struct.RleMap.html#method.iter) method.
+#[derive(Debug, Clone)]
+pub struct Iter<'a, K: 'a, V: 'a> {
+    map: &'a RleMap<K, V>,
+    run: usize,
+}
+
+impl<'a, K: Copy + 'a, V: 'a> Iterator for Iter<'a, K, V> {
+    type Item = (Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let map = self.map;
+        let run = self.run;
+        let value = map.values.get(run)?;
+        self.run += 1;
+        Some((map.starts[run]..map.ends[run], value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.map.values.len() - self.run;
+        (len, Some(len))
+    }
+}
+
+impl<'a, K: Copy + 'a, V: 'a> ExactSizeIterator for Iter<'a, K, V> { }
+
+/// Immutable `RleMap` iterator over the ranges overlapping a range.
+///
+/// Can be obtained from the [`range`](struct.RleMap.html#method.range) method.
+#[derive(Debug, Clone)]
+pub struct RangeIter<'a, K: 'a, V: 'a> {
+    map: &'a RleMap<K, V>,
+    run: usize,
+    range: Range<K>,
+}
+
+impl<'a, K: Step + 'a, V: 'a> Iterator for RangeIter<'a, K, V> {
+    type Item = (Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let map = self.map;
+        let run = self.run;
+        let start = cmp::max(*map.starts.get(run)?, self.range.start);
+        if start >= self.range.end {
+            return None
+        }
+        self.run += 1;
+        let end = cmp::min(map.ends[run], self.range.end);
+        Some((start..end, &map.values[run]))
+    }
+}
+
+/// Iterator over the parts of a range that no range of an `RleMap` contains.
+///
+/// Can be obtained from the [`gaps`](struct.RleMap.html#method.gaps) method.
+#[derive(Debug, Clone)]
+pub struct Gaps<'a, K: 'a, V: 'a> {
+    map: &'a RleMap<K, V>,
+    run: usize,
+    // the part of the range that is left
+    range: Range<K>,
+}
+
+impl<'a, K: Step + 'a, V: 'a> Iterator for Gaps<'a, K, V> {
+    type Item = Range<K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let map = self.map;
+        while self.range.start < self.range.end {
+            let start = self.range.start;
+            match map.starts.get(self.run) {
+                Some(&run_start) if run_start <= start => {
+                    self.range.start = map.ends[self.run];
+                    self.run += 1;
+                }
+                Some(&run_start) if run_start < self.range.end => {
+                    self.range.start = run_start;
+                    return Some(start..run_start)
+                }
+                _ => {
+                    self.range.start = self.range.end;
+                    return Some(start..self.range.end)
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::{same_as, Rng};
+
+    fn assert_consistent<K: Step, V: Eq>(map: &RleMap<K, V>) {
+        for run in 0..map.values.len() {
+            assert!(map.starts[run] < map.ends[run]);
+            if run > 0 {
+                assert!(map.ends[run - 1] <= map.starts[run]);
+                assert!(map.ends[run - 1] < map.starts[run] || map.values[run - 1] != map.values[run]);
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_vec() {
+        // keys -200..200 are the positions of the reference vector
+        let mut v: Vec<Option<u8>> = vec![None; 400];
+        let mut map = RleMap::new();
+        same_as(11, 5_000, &mut v, &mut map, |rng, v, map| {
+            let start = rng.below(v.len() + 1);
+            let end = start + rng.below(v.len() + 1 - start).min(30);
+            let value = match rng.below(4) {
+                0 => None,
+                n => Some(n as u8),
+            };
+            for x in &mut v[start..end] {
+                *x = value;
+            }
+            let range = start as i32 - 200..end as i32 - 200;
+            match value {
+                Some(value) => map.insert_range(range, value),
+                None => map.remove_range(range),
+            }
+        }, |_, map| assert_consistent(map));
+        for (i, x) in v.iter().enumerate() {
+            assert_eq!(map.get(i as i32 - 200), x.as_ref());
+        }
+
+        let mut values = vec![None; 400];
+        for (range, value) in &map {
+            for key in range {
+                values[(key + 200) as usize] = Some(*value);
+            }
+        }
+        assert_eq!(values, v);
+
+        // range and gaps together cover the queried range
+        let mut rng = Rng::new(12);
+        for _ in 0..200 {
+            let start = rng.below(v.len() + 1) as i32 - 200;
+            let end = start + rng.below(60) as i32;
+            let mut covered = map.range(start..end).map(|(range, _)| range)
+                .chain(map.gaps(start..end))
+                .collect::<Vec<_>>();
+            covered.sort_by_key(|range| range.start);
+            let mut key = start;
+            for range in covered {
+                assert_eq!(range.start, key);
+                assert!(range.start < range.end);
+                key = range.end;
+            }
+            assert_eq!(key, end.max(start));
+            for range in map.gaps(start..end) {
+                assert!(range.clone().all(|key| !map.contains_key(key)));
+            }
+        }
+
+        let copy: RleMap<i32, u8> = map.iter().map(|(range, value)| (range, *value)).collect();
+        assert_eq!(copy, map);
+    }
+
+    #[test]
+    fn merging_ranges() {
+        let mut map = RleMap::new();
+        map.insert_range(10u64..20, 1);
+        map.insert_range(30..40, 1);
+        map.insert_range(20..30, 1);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(10..40, &1)]);
+
+        map.insert(25, 2);
+        assert_eq!(map.len(), 3);
+        map.insert(25, 1);
+        assert_eq!(map.len(), 1);
+
+        // keys at the end of the type
+        map.insert_range(u64::MAX - 1..u64::MAX, 3);
+        assert_eq!(map.get(u64::MAX - 1), Some(&3));
+        assert_eq!(map.get(u64::MAX), None);
+        assert_eq!(map.gaps(40..u64::MAX).collect::<Vec<_>>(), vec![40..u64::MAX - 1]);
+        assert_eq!(map.range(0..u64::MAX).count(), 2);
+
+        map.insert_range(50..50, 4);
+        assert_eq!(map.len(), 2);
+        map.remove_range(0..u64::MAX);
+        assert!(map.is_empty());
+        assert_eq!(map, RleMap::default());
+
+        let rle: RleVec<char> = "aabbba".chars().collect();
+        let map = RleMap::from_rle(-3i8, &rle);
+        assert_eq!(map.range(-10..10).collect::<Vec<_>>(), vec![(-3..-1, &'a'), (-1..2, &'b'), (2..3, &'a')]);
+        assert_eq!(u8::MAX.forward(1), None);
+        assert_eq!(250u8.forward(300), None);
+
+        // a signed key steps over more than its positive maximum
+        assert_eq!((-100i8).forward(200), Some(100));
+        assert_eq!(i8::MIN.forward(255), Some(i8::MAX));
+        assert_eq!((-100i8).forward(228), None);
+        assert_eq!(i8::MIN.forward(256), None);
+
+        let rle: RleVec<char> = std::iter::repeat_n('a', 150).chain(std::iter::repeat_n('b', 50)).collect();
+        let map = RleMap::from_rle(-100i8, &rle);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(-100..50, &'a'), (50..100, &'b')]);
+    }
+}
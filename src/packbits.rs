@@ -0,0 +1,432 @@
+//! The PackBits run-length encoding used by TIFF and Apple's legacy file formats.
+//!
+//! A packed stream is a sequence of packets, each starting with a header byte `n` read as an
+//! `i8`:
+//!
+//! * `0..=127`: the next `n + 1` bytes are copied literally
+//! * `-127..=-1`: the next byte is repeated `1 - n` times
+//! * `-128`: no operation
+//!
+//! # Example
+//! ```
+//! # use rle_vec::{RleVec, packbits};
+//! let rle = RleVec::from(&b"aaaaabcd"[..]);
+//!
+//! let packed = packbits::encode(&rle);
+//! assert_eq!(packed, vec![0xfc, b'a', 2, b'b', b'c', b'd']);
+//! assert_eq!(packbits::decode(&packed).unwrap(), rle);
+//! ```
+
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use super::{leading_run, RleIndex, RleVec, Run};
+
+// the longest literal or repeated run in a packet
+const MAX_PACKET: usize = 128;
+const NO_OP: u8 = 0x80;
+const OUTPUT_BUFFER: usize = 8 * 1024;
+
+/// The error returned when a packed stream can not be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The stream ends inside the packet at this offset.
+    Truncated {
+        /// The offset of the header byte of the packet.
+        offset: usize,
+    },
+    /// The packet at this offset makes the decoded length overflow the index type.
+    IndexOverflow {
+        /// The offset of the header byte of the packet.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated { offset } =>
+                write!(f, "the PackBits packet at offset {} is truncated", offset),
+            DecodeError::IndexOverflow { offset } =>
+                write!(f, "the PackBits packet at offset {} overflows the index type", offset),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Splits runs into PackBits packets.
+///
+/// Runs of three or more bytes become repeat packets and single bytes are collected in literal
+/// packets. A run of two bytes is added to a pending literal, a repeat packet would not be
+/// shorter and splits the literal.
+#[derive(Debug, Default)]
+struct Encoder {
+    current: Option<Run<u8>>,
+    literal: Vec<u8>,
+}
+
+impl Encoder {
+    fn push_run(&mut self, len: usize, value: u8, out: &mut Vec<u8>) {
+        match self.current {
+            Some(ref mut run) if run.value == value => run.len += len,
+            _ => {
+                self.emit_current(out);
+                self.current = Some(Run { len, value });
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        self.emit_current(out);
+        self.flush_literal(out);
+    }
+
+    fn emit_current(&mut self, out: &mut Vec<u8>) {
+        let Run { mut len, value } = match self.current.take() {
+            Some(run) => run,
+            None => return,
+        };
+        if len == 1 || (len == 2 && !self.literal.is_empty()) {
+            for _ in 0..len {
+                self.push_literal(value, out);
+            }
+            return
+        }
+
+        self.flush_literal(out);
+        while len > 1 {
+            let n = cmp::min(len, MAX_PACKET);
+            out.push((1 - n as isize) as u8);
+            out.push(value);
+            len -= n;
+        }
+        if len == 1 {
+            self.push_literal(value, out);
+        }
+    }
+
+    fn push_literal(&mut self, value: u8, out: &mut Vec<u8>) {
+        self.literal.push(value);
+        if self.literal.len() == MAX_PACKET {
+            self.flush_literal(out);
+        }
+    }
+
+    fn flush_literal(&mut self, out: &mut Vec<u8>) {
+        if !self.literal.is_empty() {
+            out.push((self.literal.len() - 1) as u8);
+            out.extend_from_slice(&self.literal);
+            self.literal.clear();
+        }
+    }
+}
+
+/// Encodes the bytes of an `RleVec` as PackBits.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, packbits};
+/// let rle = RleVec::from(&[0u8; 300][..]);
+/// assert_eq!(packbits::encode(&rle), vec![0x81, 0, 0x81, 0, 0xd5, 0]);
+/// ```
+pub fn encode<I: RleIndex>(rle: &RleVec<u8, I>) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut encoder = Encoder::default();
+    for run in rle.runs() {
+        encoder.push_run(run.len, *run.value, &mut out);
+    }
+    encoder.finish(&mut out);
+    out
+}
+
+/// Decodes a PackBits stream into an `RleVec`.
+///
+/// Returns an error if the stream ends inside a packet or the decoded length overflows the
+/// index type.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, packbits};
+/// let rle: RleVec<u8> = packbits::decode(&[0xfe, 7, 0x80, 1, 8, 9]).unwrap();
+/// assert_eq!(rle.to_vec(), vec![7, 7, 7, 8, 9]);
+///
+/// let error = packbits::decode::<usize>(&[0xfe, 7, 3, 8, 9]).unwrap_err();
+/// assert_eq!(error, packbits::DecodeError::Truncated { offset: 2 });
+/// ```
+pub fn decode<I: RleIndex>(packed: &[u8]) -> Result<RleVec<u8, I>, DecodeError> {
+    let mut rle = RleVec::default();
+    let mut offset = 0;
+    while offset < packed.len() {
+        let header = packed[offset] as i8;
+        let truncated = DecodeError::Truncated { offset };
+        let overflow = DecodeError::IndexOverflow { offset };
+        offset += 1;
+        match header {
+            -128 => (),
+            n if n < 0 => {
+                let value = *packed.get(offset).ok_or(truncated)?;
+                rle.try_push_n((1 - n as isize) as usize, value).map_err(|_| overflow)?;
+                offset += 1;
+            }
+            n => {
+                let literal = packed.get(offset..offset + n as usize + 1).ok_or(truncated)?;
+                // writing only fails if the literal does not fit the index type
+                rle.write_all(literal).map_err(|_| overflow)?;
+                offset += literal.len();
+            }
+        }
+    }
+    Ok(rle)
+}
+
+/// A writer that encodes the bytes written to it as PackBits into another writer.
+///
+/// The last packet is only written by [`finish`](#method.finish) or `flush`, flushing ends the
+/// pending packets early.
+///
+/// # Example
+/// ```
+/// # use std::io::Write;
+/// # use rle_vec::packbits::PackBitsWriter;
+/// let mut writer = PackBitsWriter::new(Vec::new());
+/// writer.write_all(b"aaa").unwrap();
+/// writer.write_all(b"aab").unwrap();
+///
+/// assert_eq!(writer.finish().unwrap(), vec![0xfc, b'a', 0, b'b']);
+/// ```
+#[derive(Debug)]
+pub struct PackBitsWriter<W: Write> {
+    inner: W,
+    encoder: Encoder,
+    out: Vec<u8>,
+}
+
+impl<W: Write> PackBitsWriter<W> {
+    /// Creates a writer that writes the packets to `inner`.
+    pub fn new(inner: W) -> Self {
+        PackBitsWriter { inner, encoder: Encoder::default(), out: Vec::new() }
+    }
+
+    /// Writes the pending packets and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.encoder.finish(&mut self.out);
+        self.inner.write_all(&self.out)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for PackBitsWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let n = leading_run(rest, 1);
+            self.encoder.push_run(n, rest[0], &mut self.out);
+            rest = &rest[n..];
+        }
+        if self.out.len() >= OUTPUT_BUFFER {
+            self.inner.write_all(&self.out)?;
+            self.out.clear();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.finish(&mut self.out);
+        self.inner.write_all(&self.out)?;
+        self.out.clear();
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Packet {
+    Header,
+    Literal(usize),
+    Repeat(usize, u8),
+}
+
+/// A reader that decodes a PackBits stream from another reader.
+///
+/// Header bytes are read one at a time, wrap the inner reader in a `BufReader` when reading
+/// from a file. A stream that ends inside a packet returns an `UnexpectedEof` error.
+///
+/// # Example
+/// ```
+/// # use std::io::Read;
+/// # use rle_vec::packbits::PackBitsReader;
+/// let packed = [0xfc, b'a', 0, b'b'];
+/// let mut decoded = String::new();
+/// PackBitsReader::new(&packed[..]).read_to_string(&mut decoded).unwrap();
+///
+/// assert_eq!(decoded, "aaaaab");
+/// ```
+#[derive(Debug)]
+pub struct PackBitsReader<R: Read> {
+    inner: R,
+    packet: Packet,
+}
+
+impl<R: Read> PackBitsReader<R> {
+    /// Creates a reader that decodes the packets read from `inner`.
+    pub fn new(inner: R) -> Self {
+        PackBitsReader { inner, packet: Packet::Header }
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.inner.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated PackBits packet")
+}
+
+impl<R: Read> Read for PackBitsReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0)
+        }
+        loop {
+            match self.packet {
+                Packet::Header => {
+                    let header = match self.read_byte()? {
+                        Some(header) => header,
+                        None => return Ok(0),
+                    };
+                    self.packet = match header {
+                        NO_OP => Packet::Header,
+                        n if n > NO_OP => {
+                            let value = self.read_byte()?.ok_or_else(truncated)?;
+                            Packet::Repeat(257 - n as usize, value)
+                        }
+                        n => Packet::Literal(n as usize + 1),
+                    };
+                }
+                Packet::Literal(remaining) => {
+                    let len = cmp::min(remaining, buf.len());
+                    let n = self.inner.read(&mut buf[..len])?;
+                    if n == 0 {
+                        return Err(truncated())
+                    }
+                    self.packet = if n == remaining { Packet::Header } else { Packet::Literal(remaining - n) };
+                    return Ok(n)
+                }
+                Packet::Repeat(remaining, value) => {
+                    let n = cmp::min(remaining, buf.len());
+                    buf[..n].fill(value);
+                    self.packet = if n == remaining { Packet::Header } else { Packet::Repeat(remaining - n, value) };
+                    return Ok(n)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the example from Apple's technical note TN1023
+    const UNPACKED: [u8; 24] = [
+        0xaa, 0xaa, 0xaa, 0x80, 0x00, 0x2a, 0xaa, 0xaa, 0xaa, 0xaa, 0x80, 0x00,
+        0x2a, 0x22, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    ];
+    const PACKED: [u8; 15] = [
+        0xfe, 0xaa, 0x02, 0x80, 0x00, 0x2a, 0xfd, 0xaa, 0x03, 0x80, 0x00, 0x2a, 0x22, 0xf7, 0xaa,
+    ];
+
+    fn roundtrip(data: &[u8]) {
+        let rle: RleVec<u8> = RleVec::from(data);
+        let packed = encode(&rle);
+        assert_eq!(decode::<usize>(&packed).unwrap(), rle);
+
+        let mut writer = PackBitsWriter::new(Vec::new());
+        for chunk in data.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), packed);
+
+        let mut decoded = Vec::new();
+        PackBitsReader::new(&packed[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn known_vectors() {
+        let rle = RleVec::from(&UNPACKED[..]);
+        assert_eq!(encode(&rle), PACKED.to_vec());
+        assert_eq!(decode::<usize>(&PACKED).unwrap(), rle);
+        roundtrip(&UNPACKED);
+    }
+
+    #[test]
+    fn packet_limits() {
+        roundtrip(&[]);
+        roundtrip(&[1]);
+        roundtrip(&[1, 1]);
+        roundtrip(&[0; 128]);
+        roundtrip(&[0; 129]);
+        roundtrip(&[0; 257]);
+        let counting: Vec<u8> = (0..=255).chain(0..=255).collect();
+        roundtrip(&counting);
+
+        // a two byte run does not split a literal
+        let packed = encode(&RleVec::<u8>::from(&[1, 2, 2, 3][..]));
+        assert_eq!(packed, vec![3, 1, 2, 2, 3]);
+
+        // literals hold at most 128 bytes
+        let packed = encode(&RleVec::<u8>::from(&counting[..200]));
+        assert_eq!(packed[0], 127);
+        assert_eq!(packed[129], 71);
+    }
+
+    #[test]
+    fn truncated_streams() {
+        for end in 1..PACKED.len() {
+            let result = decode::<usize>(&PACKED[..end]);
+            let mut decoded = Vec::new();
+            let read = PackBitsReader::new(&PACKED[..end]).read_to_end(&mut decoded);
+            // the packets end at these offsets
+            if [2, 6, 8, 13, 15].contains(&end) {
+                assert!(result.is_ok());
+                assert!(read.is_ok());
+            } else {
+                assert!(result.is_err());
+                assert_eq!(read.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+            }
+        }
+    }
+
+    #[test]
+    fn index_overflow() {
+        // 512 repeat packets of 128 bytes fill a u16 index
+        let mut packed = [0x81, 7].repeat(512);
+        assert_eq!(decode::<u16>(&packed).unwrap().len(), 65_536);
+
+        packed.extend_from_slice(&[0x81, 7]);
+        assert_eq!(decode::<u16>(&packed), Err(DecodeError::IndexOverflow { offset: 1024 }));
+        assert_eq!(decode::<u32>(&packed).unwrap().len(), 65_664);
+
+        packed.truncate(1024);
+        packed.extend_from_slice(&[0, 7]);
+        let error = decode::<u16>(&packed).unwrap_err();
+        assert_eq!(error, DecodeError::IndexOverflow { offset: 1024 });
+        assert_eq!(error.to_string(), "the PackBits packet at offset 1024 overflows the index type");
+    }
+}
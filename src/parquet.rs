@@ -0,0 +1,287 @@
+//! The RLE/bit-packing hybrid encoding Parquet uses for levels and dictionary indices.
+//!
+//! The encoded data is a sequence of runs, each starting with a ULEB128 header:
+//!
+//! * `len << 1`: an RLE run of `len` copies of the value that follows in `ceil(bit_width / 8)`
+//!   little-endian bytes
+//! * `groups << 1 | 1`: a bit-packed run of `8 * groups` values of `bit_width` bits each, packed
+//!   from the least significant bit
+//!
+//! The data holds no value count, the last bit-packed group is padded with zeros. The four byte
+//! length prefix of data page levels is not part of the encoding.
+//!
+//! # Example
+//! ```
+//! # use rle_vec::{RleVec, parquet};
+//! let levels: RleVec<u32> = vec![1; 100].into_iter().collect();
+//!
+//! let data = parquet::encode(&levels, 1);
+//! assert_eq!(data, vec![0xc8, 0x01, 0x01]);
+//! assert_eq!(parquet::decode(&data, 1, 100).unwrap(), levels);
+//! ```
+
+use std::cmp;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use binary::{read_varint, write_varint, FormatError};
+use super::{RleIndex, RleVec};
+
+// the values of a bit-packed group, runs at least this long are RLE encoded
+const GROUP: usize = 8;
+
+/// The error returned when decoding malformed hybrid encoded data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The run at this offset is cut off by the end of the data.
+    Truncated {
+        /// The offset of the run.
+        offset: usize,
+    },
+    /// The header of the run at this offset is not a valid ULEB128 number.
+    InvalidHeader {
+        /// The offset of the run.
+        offset: usize,
+    },
+    /// The run at this offset holds no values.
+    EmptyRun {
+        /// The offset of the run.
+        offset: usize,
+    },
+    /// The value of the RLE run at this offset does not fit the bit width.
+    ValueOutOfRange {
+        /// The offset of the run.
+        offset: usize,
+    },
+    /// The run at this offset holds more values than are left to decode.
+    TooManyValues {
+        /// The offset of the run.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated { offset } =>
+                write!(f, "the run at offset {} is truncated", offset),
+            DecodeError::InvalidHeader { offset } =>
+                write!(f, "the run at offset {} has an invalid header", offset),
+            DecodeError::EmptyRun { offset } =>
+                write!(f, "the run at offset {} is empty", offset),
+            DecodeError::ValueOutOfRange { offset } =>
+                write!(f, "the value of the run at offset {} does not fit the bit width", offset),
+            DecodeError::TooManyValues { offset } =>
+                write!(f, "the run at offset {} holds more values than expected", offset),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+fn fits(value: u32, bit_width: u8) -> bool {
+    bit_width >= 32 || value >> bit_width == 0
+}
+
+fn value_bytes(bit_width: u8) -> usize {
+    (bit_width as usize).div_ceil(8)
+}
+
+/// Encodes the values of an `RleVec<u32>` with `bit_width` bits each.
+///
+/// Runs of eight or more values become RLE runs, shorter runs are collected in bit-packed
+/// groups. A long run first completes the pending bit-packed group, so the groups only need
+/// padding at the end.
+///
+/// # Panics
+/// Panics if `bit_width` is larger than 32 or a value does not fit in `bit_width` bits.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, parquet};
+/// // the example from the format specification, a bit-packed group of 0 to 7
+/// let rle: RleVec<u32> = (0..8).collect();
+/// assert_eq!(parquet::encode(&rle, 3), vec![0x03, 0x88, 0xc6, 0xfa]);
+/// ```
+pub fn encode<I: RleIndex>(rle: &RleVec<u32, I>, bit_width: u8) -> Vec<u8> {
+    assert!(bit_width <= 32, "the bit width {} is larger than 32", bit_width);
+    let mut out = Vec::new();
+    let mut packed = Vec::new();
+    for run in rle.runs() {
+        let value = *run.value;
+        assert!(fits(value, bit_width), "the value {} does not fit in {} bits", value, bit_width);
+        let mut len = run.len;
+        if len >= GROUP {
+            let fill = cmp::min((GROUP - packed.len() % GROUP) % GROUP, len);
+            packed.extend(std::iter::repeat_n(value, fill));
+            len -= fill;
+        }
+        if len >= GROUP {
+            write_packed(&mut packed, bit_width, &mut out);
+            write_varint(&mut out, (len as u64) << 1).expect("writing to a Vec");
+            out.extend_from_slice(&value.to_le_bytes()[..value_bytes(bit_width)]);
+        } else {
+            packed.extend(std::iter::repeat_n(value, len));
+        }
+    }
+    write_packed(&mut packed, bit_width, &mut out);
+    out
+}
+
+fn write_packed(packed: &mut Vec<u32>, bit_width: u8, out: &mut Vec<u8>) {
+    if packed.is_empty() {
+        return
+    }
+    let groups = packed.len().div_ceil(GROUP);
+    packed.resize(groups * GROUP, 0);
+    write_varint(out, (groups as u64) << 1 | 1).expect("writing to a Vec");
+
+    let (mut bits, mut pending) = (0u64, 0);
+    for &value in packed.iter() {
+        bits |= u64::from(value) << pending;
+        pending += bit_width;
+        while pending >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            pending -= 8;
+        }
+    }
+    packed.clear();
+}
+
+/// Decodes `len` values of `bit_width` bits each.
+///
+/// Decoding stops after `len` values, the padding of the last bit-packed group and the bytes
+/// that follow are ignored.
+///
+/// # Panics
+/// Panics if `bit_width` is larger than 32 or `len` overflows the index type.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, parquet};
+/// // a bit-packed group of 1, 2, 3 and five padding values
+/// let rle: RleVec<u32> = parquet::decode(&[0x03, 0xd1, 0x00, 0x00], 3, 3).unwrap();
+/// assert_eq!(rle.to_vec(), vec![1, 2, 3]);
+///
+/// assert_eq!(parquet::decode::<usize>(&[0x08, 0x09], 3, 4),
+///            Err(parquet::DecodeError::ValueOutOfRange { offset: 0 }));
+/// ```
+pub fn decode<I: RleIndex>(data: &[u8], bit_width: u8, len: usize)
+    -> Result<RleVec<u32, I>, DecodeError>
+{
+    assert!(bit_width <= 32, "the bit width {} is larger than 32", bit_width);
+    let mut rle = RleVec::default();
+    let mut remaining = len;
+    let mut rest = data;
+    while remaining > 0 {
+        let offset = data.len() - rest.len();
+        let header = read_varint(&mut rest).map_err(|e| match e {
+            FormatError::InvalidVarint => DecodeError::InvalidHeader { offset },
+            _ => DecodeError::Truncated { offset },
+        })?;
+        let count = usize::try_from(header >> 1).map_err(|_| DecodeError::TooManyValues { offset })?;
+        if count == 0 {
+            return Err(DecodeError::EmptyRun { offset })
+        }
+
+        if header & 1 == 0 {
+            if count > remaining {
+                return Err(DecodeError::TooManyValues { offset })
+            }
+            let size = value_bytes(bit_width);
+            let bytes = rest.get(..size).ok_or(DecodeError::Truncated { offset })?;
+            let mut value = [0; 4];
+            value[..size].copy_from_slice(bytes);
+            let value = u32::from_le_bytes(value);
+            if !fits(value, bit_width) {
+                return Err(DecodeError::ValueOutOfRange { offset })
+            }
+            rle.push_n(count, value);
+            remaining -= count;
+            rest = &rest[size..];
+        } else {
+            // only the padding of the last group may exceed the remaining values
+            if count > remaining.div_ceil(GROUP) {
+                return Err(DecodeError::TooManyValues { offset })
+            }
+            let size = count * bit_width as usize;
+            let bytes = rest.get(..size).ok_or(DecodeError::Truncated { offset })?;
+            let values = cmp::min(count * GROUP, remaining);
+            let mask = if bit_width == 32 { u64::from(u32::MAX) } else { (1 << bit_width) - 1 };
+            let (mut bits, mut pending, mut bytes_iter) = (0u64, 0, bytes.iter());
+            for _ in 0..values {
+                while pending < bit_width {
+                    bits |= u64::from(*bytes_iter.next().expect("packed bytes")) << pending;
+                    pending += 8;
+                }
+                rle.push((bits & mask) as u32);
+                bits >>= bit_width;
+                pending -= bit_width;
+            }
+            remaining -= values;
+            rest = &rest[size..];
+        }
+    }
+    Ok(rle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::Rng;
+
+    #[test]
+    fn known_vectors() {
+        let rle: RleVec<u32> = (0..8).collect();
+        let data = [0x03, 0x88, 0xc6, 0xfa];
+        assert_eq!(encode(&rle, 3), data.to_vec());
+        assert_eq!(decode::<usize>(&data, 3, 8).unwrap(), rle);
+
+        // 1, 2, 3 are packed with the first five 7s, the other fifteen are an RLE run
+        let rle: RleVec<u32> = vec![1, 2, 3].into_iter().chain(vec![7; 20]).collect();
+        let data = [0x03, 0xd1, 0xfe, 0xff, 0x1e, 0x07];
+        assert_eq!(encode(&rle, 3), data.to_vec());
+        assert_eq!(decode::<usize>(&data, 3, 23).unwrap(), rle);
+
+        // values take up to four little-endian bytes
+        let rle: RleVec<u32, u16> = vec![0x12345; 8].into_iter().collect();
+        let data = [0x10, 0x45, 0x23, 0x01];
+        assert_eq!(encode(&rle, 17), data.to_vec());
+        assert_eq!(decode::<u16>(&data, 17, 8).unwrap(), rle);
+
+        // a bit width of zero stores no value bytes
+        let rle: RleVec<u32> = vec![0; 3].into_iter().collect();
+        assert_eq!(encode(&rle, 0), vec![0x03]);
+        assert_eq!(decode::<usize>(&[0x03], 0, 3).unwrap(), rle);
+    }
+
+    #[test]
+    fn roundtrips() {
+        let mut rng = Rng::new(0x2545_f491);
+        for &bit_width in &[1, 2, 5, 8, 13, 24, 32] {
+            let max = if bit_width == 32 { u32::MAX } else { (1 << bit_width) - 1 };
+            let mut rle = RleVec::new();
+            for _ in 0..200 {
+                let len = if rng.below(3) == 0 { rng.below(40) + 1 } else { rng.below(4) + 1 };
+                let value = rng.below(max as usize) as u32;
+                rle.push_n(len, value.max(rng.below(2) as u32 * max));
+            }
+            let data = encode(&rle, bit_width);
+            assert_eq!(decode::<usize>(&data, bit_width, rle.len()).unwrap(), rle);
+        }
+    }
+
+    #[test]
+    fn malformed_data() {
+        let data = [0x03, 0xd1, 0xfe, 0xff, 0x1e, 0x07];
+        assert_eq!(decode::<usize>(&data[..3], 3, 23), Err(DecodeError::Truncated { offset: 0 }));
+        assert_eq!(decode::<usize>(&data[..4], 3, 23), Err(DecodeError::Truncated { offset: 4 }));
+        assert_eq!(decode::<usize>(&data[..5], 3, 23), Err(DecodeError::Truncated { offset: 4 }));
+        assert_eq!(decode::<usize>(&data, 3, 22), Err(DecodeError::TooManyValues { offset: 4 }));
+        assert_eq!(decode::<usize>(&[0x05, 0, 0, 0], 1, 8), Err(DecodeError::TooManyValues { offset: 0 }));
+        assert_eq!(decode::<usize>(&[0x00, 0x01], 1, 8), Err(DecodeError::EmptyRun { offset: 0 }));
+        assert_eq!(decode::<usize>(&[0xff; 11], 1, 8), Err(DecodeError::InvalidHeader { offset: 0 }));
+    }
+}
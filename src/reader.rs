@@ -0,0 +1,240 @@
+//! Reading the bytes of an `RleVec<u8>` as a stream.
+
+use std::cmp;
+use std::fmt;
+use std::convert::TryFrom;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::mem;
+
+use super::{RleIndex, RleVec};
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A reader over the bytes of an `RleVec<u8>`, the counterpart of its `io::Write` implementation.
+///
+/// Reading fills the output with whole runs at once instead of decoding byte by byte. The reader
+/// implements `BufRead` with an internal buffer that is only allocated when `fill_buf` is used,
+/// and `Seek`, which looks up the run of the new position with a binary search.
+///
+/// # Example
+/// ```
+/// # use std::io::{Read, Seek, SeekFrom};
+/// # use rle_vec::{RleVec, RleReader};
+/// let rle = RleVec::from(&b"aaaabbbcc"[..]);
+/// let mut reader = RleReader::new(&rle);
+///
+/// let mut buf = [0; 5];
+/// reader.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"aaaab");
+///
+/// reader.seek(SeekFrom::End(-3)).unwrap();
+/// let mut rest = String::new();
+/// reader.read_to_string(&mut rest).unwrap();
+/// assert_eq!(rest, "bcc");
+/// ```
+#[derive(Clone)]
+pub struct RleReader<'a, I: 'a = usize> {
+    rle: &'a RleVec<u8, I>,
+    // the position of the next byte returned, may be past the end after seeking
+    pos: u64,
+    // the run containing pos, if pos is in bounds
+    run: usize,
+    buf: Vec<u8>,
+    buf_start: usize,
+    buf_end: usize,
+}
+
+impl<'a, I: RleIndex> RleReader<'a, I> {
+    /// Creates a reader positioned at the start of the vector.
+    pub fn new(rle: &'a RleVec<u8, I>) -> Self {
+        RleReader { rle, pos: 0, run: 0, buf: Vec::new(), buf_start: 0, buf_end: 0 }
+    }
+
+    /// Returns the vector that is read.
+    pub fn get_ref(&self) -> &'a RleVec<u8, I> {
+        self.rle
+    }
+
+    /// Returns the current position.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    fn remaining(&self) -> usize {
+        match usize::try_from(self.pos) {
+            Ok(pos) if pos < self.rle.len() => self.rle.len() - pos,
+            _ => 0,
+        }
+    }
+
+    /// Fills `out` with the bytes from the current position without advancing.
+    fn copy_runs(&self, out: &mut [u8]) -> usize {
+        let len = cmp::min(out.len(), self.remaining());
+        let mut at = self.pos as usize;
+        let mut run = self.run;
+        let mut written = 0;
+        while written < len {
+            let n = cmp::min(self.rle.end(run) + 1 - at, len - written);
+            out[written..written + n].fill(self.rle.values[run]);
+            written += n;
+            at += n;
+            run += 1;
+        }
+        written
+    }
+
+    fn advance(&mut self, amt: usize) {
+        self.pos += amt as u64;
+        let pos = self.pos as usize;
+        while self.run < self.rle.runs_len() && self.rle.end(self.run) < pos {
+            self.run += 1;
+        }
+    }
+}
+
+impl<'a, I: RleIndex> fmt::Debug for RleReader<'a, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RleReader").field("position", &self.pos).field("len", &self.rle.len()).finish()
+    }
+}
+
+impl<'a, I: RleIndex> Read for RleReader<'a, I> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = if self.buf_start < self.buf_end {
+            let n = cmp::min(out.len(), self.buf_end - self.buf_start);
+            out[..n].copy_from_slice(&self.buf[self.buf_start..self.buf_start + n]);
+            self.buf_start += n;
+            n
+        } else {
+            self.copy_runs(out)
+        };
+        self.advance(n);
+        Ok(n)
+    }
+}
+
+impl<'a, I: RleIndex> BufRead for RleReader<'a, I> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.buf_start == self.buf_end {
+            let mut buf = mem::take(&mut self.buf);
+            buf.resize(BUFFER_SIZE, 0);
+            self.buf_end = self.copy_runs(&mut buf);
+            self.buf_start = 0;
+            self.buf = buf;
+        }
+        Ok(&self.buf[self.buf_start..self.buf_end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = cmp::min(amt, self.buf_end - self.buf_start);
+        self.buf_start += amt;
+        self.advance(amt);
+    }
+}
+
+impl<'a, I: RleIndex> Seek for RleReader<'a, I> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => (n, 0),
+            SeekFrom::End(n) => (self.rle.len() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.unsigned_abs())
+        };
+        let pos = pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position"))?;
+
+        self.pos = pos;
+        self.buf_start = 0;
+        self.buf_end = 0;
+        if self.remaining() > 0 {
+            self.run = self.rle.run_index(pos as usize);
+        }
+        Ok(pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Vec<u8>, RleVec<u8, u32>) {
+        let v: Vec<u8> = (0..20_000u32).map(|i| (i / 7 % 5) as u8).collect();
+        let rle = v.iter().cloned().collect();
+        (v, rle)
+    }
+
+    #[test]
+    fn reads_like_a_slice() {
+        let (v, rle) = sample();
+        let mut out = Vec::new();
+        RleReader::new(&rle).read_to_end(&mut out).unwrap();
+        assert_eq!(out, v);
+
+        let mut reader = RleReader::new(&rle);
+        let mut small = [0; 3];
+        let mut out = Vec::new();
+        loop {
+            let n = reader.read(&mut small).unwrap();
+            if n == 0 { break }
+            out.extend_from_slice(&small[..n]);
+        }
+        assert_eq!(out, v);
+    }
+
+    #[test]
+    fn buffered_reads() {
+        let (v, rle) = sample();
+        let mut reader = RleReader::new(&rle);
+        let mut out = Vec::new();
+        let mut byte = [0];
+        loop {
+            // alternate between buffered and direct reads
+            let available = reader.fill_buf().unwrap().len();
+            if available == 0 { break }
+            let chunk = reader.fill_buf().unwrap()[..cmp::min(available, 100)].to_vec();
+            out.extend_from_slice(&chunk);
+            reader.consume(chunk.len());
+            if reader.read(&mut byte).unwrap() == 1 {
+                out.push(byte[0]);
+            }
+        }
+        assert_eq!(out, v);
+        assert_eq!(reader.position(), v.len() as u64);
+    }
+
+    #[test]
+    fn seeking() {
+        let (v, rle) = sample();
+        let mut reader = RleReader::new(&rle);
+        let mut buf = [0; 10];
+
+        assert_eq!(reader.seek(SeekFrom::Start(1234)).unwrap(), 1234);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &v[1234..1244]);
+
+        reader.fill_buf().unwrap();
+        assert_eq!(reader.seek(SeekFrom::Current(-100)).unwrap(), 1144);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &v[1144..1154]);
+
+        assert_eq!(reader.seek(SeekFrom::End(-4)).unwrap(), v.len() as u64 - 4);
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], &v[v.len() - 4..]);
+
+        // past the end reads nothing
+        assert_eq!(reader.seek(SeekFrom::End(10)).unwrap(), v.len() as u64 + 10);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert!(reader.fill_buf().unwrap().is_empty());
+
+        assert!(reader.seek(SeekFrom::Current(-(v.len() as i64) - 11)).is_err());
+        assert_eq!(reader.stream_position().unwrap(), v.len() as u64 + 10);
+    }
+}
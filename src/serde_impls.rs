@@ -0,0 +1,146 @@
+//! Serde support, enabled with the `serde` feature.
+//!
+//! An `RleVec` is serialized as a sequence of `[len, value]` pairs, one for every run, and a
+//! `Run` as a single `[len, value]` pair. Deserialized runs are checked like
+//! [`RleVec::from_run_lengths`](../struct.RleVec.html#method.from_run_lengths) does, and
+//! adjacent runs with equal values are merged.
+
+use std::cmp;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use super::{RleIndex, RleVec, Run};
+
+// limits the preallocation for untrusted size hints
+const MAX_PREALLOCATED_RUNS: usize = 4096;
+
+impl<T: Serialize> Serialize for Run<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.len, &self.value).serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Run<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (len, value) = <(usize, T)>::deserialize(deserializer)?;
+        Ok(Run { len, value })
+    }
+}
+
+impl<T: Serialize, I: RleIndex> Serialize for RleVec<T, I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.runs_len()))?;
+        for run in self.runs() {
+            seq.serialize_element(&run)?;
+        }
+        seq.end()
+    }
+}
+
+struct RunsVisitor<T, I> {
+    marker: PhantomData<RleVec<T, I>>,
+}
+
+impl<'de, T: Deserialize<'de> + Eq, I: RleIndex> Visitor<'de> for RunsVisitor<T, I> {
+    type Value = RleVec<T, I>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of [len, value] runs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let capacity = cmp::min(seq.size_hint().unwrap_or(0), MAX_PREALLOCATED_RUNS);
+        let mut lengths = Vec::with_capacity(capacity);
+        let mut values = Vec::with_capacity(capacity);
+        while let Some(Run { len, value }) = seq.next_element()? {
+            lengths.push(len);
+            values.push(value);
+        }
+        let mut rle = RleVec::from_run_lengths(&lengths, values).map_err(de::Error::custom)?;
+        rle.coalesce();
+        Ok(rle)
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Eq, I: RleIndex> Deserialize<'de> for RleVec<T, I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(RunsVisitor { marker: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_tokens, assert_de_tokens, assert_de_tokens_error, Token};
+    use super::*;
+
+    #[test]
+    fn runs_format() {
+        let rle: RleVec<u8, u32> = vec![1, 1, 2].into_iter().collect();
+        assert_tokens(&rle, &[
+            Token::Seq { len: Some(2) },
+            Token::Tuple { len: 2 }, Token::U64(2), Token::U8(1), Token::TupleEnd,
+            Token::Tuple { len: 2 }, Token::U64(1), Token::U8(2), Token::TupleEnd,
+            Token::SeqEnd,
+        ]);
+
+        assert_tokens(&RleVec::<bool>::new(), &[Token::Seq { len: Some(0) }, Token::SeqEnd]);
+        assert_tokens(&Run { len: 3, value: 'a' }, &[
+            Token::Tuple { len: 2 }, Token::U64(3), Token::Char('a'), Token::TupleEnd,
+        ]);
+    }
+
+    #[test]
+    fn validates_runs() {
+        assert_de_tokens_error::<RleVec<u8>>(&[
+            Token::Seq { len: Some(2) },
+            Token::Tuple { len: 2 }, Token::U64(2), Token::U8(1), Token::TupleEnd,
+            Token::Tuple { len: 2 }, Token::U64(0), Token::U8(2), Token::TupleEnd,
+            Token::SeqEnd,
+        ], "run 1 has a length of zero");
+
+        assert_de_tokens_error::<RleVec<u8, u16>>(&[
+            Token::Seq { len: Some(1) },
+            Token::Tuple { len: 2 }, Token::U64(70_000), Token::U8(1), Token::TupleEnd,
+            Token::SeqEnd,
+        ], "the length of the RleVec overflows its index type");
+    }
+
+    // compares the raw runs, which the PartialEq of RleVec merges
+    #[derive(Debug)]
+    struct Runs(RleVec<u8>);
+
+    impl PartialEq for Runs {
+        fn eq(&self, other: &Runs) -> bool {
+            self.0.ends() == other.0.ends() && self.0.values() == other.0.values()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Runs {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            RleVec::deserialize(deserializer).map(Runs)
+        }
+    }
+
+    #[test]
+    fn merges_equal_runs() {
+        let rle = RleVec::from_ends(vec![4usize], vec![5u8]).unwrap();
+        assert_de_tokens(&Runs(rle.clone()), &[
+            Token::Seq { len: Some(2) },
+            Token::Tuple { len: 2 }, Token::U64(2), Token::U8(5), Token::TupleEnd,
+            Token::Tuple { len: 2 }, Token::U64(3), Token::U8(5), Token::TupleEnd,
+            Token::SeqEnd,
+        ]);
+
+        let mut split = rle.clone();
+        split.cursor_mut(2).split_run_here();
+        assert_tokens(&split, &[
+            Token::Seq { len: Some(2) },
+            Token::Tuple { len: 2 }, Token::U64(2), Token::U8(5), Token::TupleEnd,
+            Token::Tuple { len: 2 }, Token::U64(3), Token::U8(5), Token::TupleEnd,
+            Token::SeqEnd,
+        ]);
+    }
+}
@@ -0,0 +1,385 @@
+//! A run-length encoded vector that only stores the runs that differ from a default value.
+
+use std::ops::{Index, Range};
+
+use super::{splice_ranges, RleIndex, RleVec, Run};
+
+/// A vector of a default value with scattered runs of other values.
+///
+/// Only the runs that differ from the default are stored, with their first and last position,
+/// so the memory used is proportional to the number of these runs, independent of the length.
+/// The gaps between them hold the default value. Neighbouring stored runs with equal values
+/// are merged, so two vectors holding the same values compare equal.
+///
+/// Finding an index is a binary search over the stored runs, setting a range replaces the
+/// runs it overlaps, which is **O(runs)** in the worst case like an `RleVec`.
+///
+/// # Example
+/// ```
+/// # use rle_vec::SparseRle;
+/// let mut sparse = SparseRle::new(1_000_000, 0u8);
+///
+/// sparse.set_range(10..20, 7);
+/// sparse.set(500_000, 3);
+/// sparse.set_range(15..18, 0);
+///
+/// assert_eq!(sparse[12], 7);
+/// assert_eq!(sparse[16], 0);
+/// assert_eq!(sparse.stored_runs_len(), 3);
+/// assert_eq!(sparse.iter().filter(|&&v| v != 0).count(), 8);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SparseRle<T> {
+    // the first and last position of every stored run
+    starts: Vec<usize>,
+    ends: Vec<usize>,
+    values: Vec<T>,
+    len: usize,
+    default: T,
+}
+
+impl<T> SparseRle<T> {
+    /// Constructs a vector of `len` default values.
+    pub fn new(len: usize, default: T) -> SparseRle<T> {
+        SparseRle { starts: Vec::new(), ends: Vec::new(), values: Vec::new(), len, default }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the default value.
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    /// Returns the number of stored runs, which differ from the default.
+    pub fn stored_runs_len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the value at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None
+        }
+        let run = self.ends.partition_point(|&end| end < index);
+        match self.starts.get(run) {
+            Some(&start) if start <= index => Some(&self.values[run]),
+            _ => Some(&self.default),
+        }
+    }
+
+    /// Returns an iterator over the values, yielding the default across the gaps.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { sparse: self, index: 0, run: 0 }
+    }
+
+    /// Returns an iterator over all runs, the stored runs and the default runs between them.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{SparseRle, Run};
+    /// let mut sparse = SparseRle::new(6, 0);
+    /// sparse.set_range(2..4, 5);
+    ///
+    /// let runs: Vec<_> = sparse.runs().collect();
+    /// assert_eq!(runs, vec![Run { len: 2, value: &0 }, Run { len: 2, value: &5 }, Run { len: 2, value: &0 }]);
+    /// ```
+    pub fn runs(&self) -> Runs<'_, T> {
+        Runs { sparse: self, index: 0, run: 0 }
+    }
+
+    /// Returns an iterator over the positions and values of the stored runs.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::SparseRle;
+    /// let mut sparse = SparseRle::new(100, 0);
+    /// sparse.set_range(20..30, 1);
+    ///
+    /// assert_eq!(sparse.stored_runs().collect::<Vec<_>>(), vec![(20..30, &1)]);
+    /// ```
+    pub fn stored_runs(&self) -> StoredRuns<'_, T> {
+        StoredRuns { sparse: self, run: 0 }
+    }
+}
+
+impl<T: Eq + Clone> SparseRle<T> {
+    /// Sets the value at `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) {
+        self.set_range(index..index + 1, value)
+    }
+
+    /// Sets all values in `range`, setting them to the default removes the stored runs.
+    ///
+    /// # Panics
+    /// Panics if the end of `range` is larger than the length.
+    pub fn set_range(&mut self, range: Range<usize>, value: T) {
+        assert!(range.end <= self.len, "range end {} out of bounds for length {}", range.end, self.len);
+        if range.start >= range.end {
+            return
+        }
+        let (start, last) = (range.start, range.end - 1);
+
+        // the ends are inclusive, a run ending at a position touches the run starting after it
+        let value = if value != self.default { Some(value) } else { None };
+        splice_ranges(&mut self.starts, &mut self.ends, &mut self.values, (start, last), value,
+                      |last| last + 1, |start| start - 1);
+    }
+
+    /// Converts an `RleVec` to a `SparseRle`, storing the runs that differ from `default`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleVec, SparseRle};
+    /// let rle = RleVec::from(&[0, 0, 4, 4, 0, 5][..]);
+    /// let sparse = SparseRle::from_rle(&rle, 0);
+    ///
+    /// assert_eq!(sparse.stored_runs_len(), 2);
+    /// assert_eq!(sparse.to_rle(), rle);
+    /// ```
+    pub fn from_rle<I: RleIndex>(rle: &RleVec<T, I>, default: T) -> SparseRle<T> {
+        let mut sparse = SparseRle::new(rle.len(), default);
+        let mut start = 0;
+        for Run { len, value } in rle.runs() {
+            if *value != sparse.default {
+                match sparse.values.last() {
+                    Some(last) if last == value && sparse.ends.last() == Some(&(start - 1)) => {
+                        *sparse.ends.last_mut().unwrap() += len;
+                    }
+                    _ => {
+                        sparse.starts.push(start);
+                        sparse.ends.push(start + len - 1);
+                        sparse.values.push(value.clone());
+                    }
+                }
+            }
+            start += len;
+        }
+        sparse
+    }
+
+    /// Converts the values to an `RleVec`.
+    pub fn to_rle(&self) -> RleVec<T> {
+        let mut rle = RleVec::new();
+        for Run { len, value } in self.runs() {
+            rle.push_n(len, value.clone());
+        }
+        rle
+    }
+}
+
+impl<T> Index<usize> for SparseRle<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index {} out of bounds for length {}", index, self.len),
+        }
+    }
+}
+
+impl<T: Eq + Clone> From<SparseRle<T>> for RleVec<T> {
+    fn from(sparse: SparseRle<T>) -> RleVec<T> {
+        sparse.to_rle()
+    }
+}
+
+impl<'a, T: 'a> IntoIterator for &'a SparseRle<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Immutable `SparseRle` iterator over the values.
+///
+/// Can be obtained from the [`iter`](struct.SparseRle.html#method.iter) method.
+#[derive(Debug, Clone)]
+pub struct Iter<'a, T: 'a> {
+    sparse: &'a SparseRle<T>,
+    index: usize,
+    // the first stored run that does not end before index
+    run: usize,
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sparse = self.sparse;
+        if self.index >= sparse.len {
+            return None
+        }
+        let value = match sparse.starts.get(self.run) {
+            Some(&start) if start <= self.index => {
+                let value = &sparse.values[self.run];
+                if sparse.ends[self.run] == self.index {
+                    self.run += 1;
+                }
+                value
+            }
+            _ => &sparse.default,
+        };
+        self.index += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.sparse.len - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for Iter<'a, T> { }
+
+/// Immutable `SparseRle` iterator over all runs, including the default runs.
+///
+/// Can be obtained from the [`runs`](struct.SparseRle.html#method.runs) method.
+#[derive(Debug, Clone)]
+pub struct Runs<'a, T: 'a> {
+    sparse: &'a SparseRle<T>,
+    index: usize,
+    run: usize,
+}
+
+impl<'a, T: 'a> Iterator for Runs<'a, T> {
+    type Item = Run<&'a T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sparse = self.sparse;
+        if self.index >= sparse.len {
+            return None
+        }
+        let run = match sparse.starts.get(self.run) {
+            Some(&start) if start == self.index => {
+                self.run += 1;
+                Run { len: sparse.ends[self.run - 1] + 1 - start, value: &sparse.values[self.run - 1] }
+            }
+            Some(&start) => Run { len: start - self.index, value: &sparse.default },
+            None => Run { len: sparse.len - self.index, value: &sparse.default },
+        };
+        self.index += run.len;
+        Some(run)
+    }
+}
+
+/// Immutable `SparseRle` iterator over the positions and values of the stored runs.
+///
+/// Can be obtained from the [`stored_runs`](struct.SparseRle.html#method.stored_runs) method.
+#[derive(Debug, Clone)]
+pub struct StoredRuns<'a, T: 'a> {
+    sparse: &'a SparseRle<T>,
+    run: usize,
+}
+
+impl<'a, T: 'a> Iterator for StoredRuns<'a, T> {
+    type Item = (Range<usize>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sparse = self.sparse;
+        let run = self.run;
+        let value = sparse.values.get(run)?;
+        self.run += 1;
+        Some((sparse.starts[run]..sparse.ends[run] + 1, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.sparse.values.len() - self.run;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for StoredRuns<'a, T> { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::same_as;
+
+    fn assert_consistent<T: Eq>(sparse: &SparseRle<T>) {
+        for run in 0..sparse.values.len() {
+            assert!(sparse.starts[run] <= sparse.ends[run]);
+            assert!(sparse.ends[run] < sparse.len);
+            assert!(sparse.values[run] != sparse.default);
+            if run > 0 {
+                assert!(sparse.ends[run - 1] < sparse.starts[run]);
+                assert!(sparse.ends[run - 1] + 1 < sparse.starts[run] || sparse.values[run - 1] != sparse.values[run]);
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_vec() {
+        let mut v = vec![0u8; 500];
+        let mut sparse = SparseRle::new(500, 0u8);
+        same_as(7, 5_000, &mut v, &mut sparse, |rng, v, sparse| {
+            let value = rng.below(3) as u8;
+            if rng.below(2) == 0 {
+                let index = rng.below(v.len());
+                v[index] = value;
+                sparse.set(index, value);
+            } else {
+                let start = rng.below(v.len() + 1);
+                let end = start + rng.below(v.len() + 1 - start).min(20);
+                for x in &mut v[start..end] {
+                    *x = value;
+                }
+                sparse.set_range(start..end, value);
+            }
+        }, |_, sparse| assert_consistent(sparse));
+        assert_eq!(sparse.iter().cloned().collect::<Vec<_>>(), v);
+        assert_eq!((0..v.len()).map(|i| sparse[i]).collect::<Vec<_>>(), v);
+        assert_eq!(sparse.get(v.len()), None);
+
+        let rle = RleVec::from(&v[..]);
+        assert_eq!(sparse.to_rle(), rle);
+        assert_eq!(sparse.runs().collect::<Vec<_>>(), rle.runs().collect::<Vec<_>>());
+        assert_eq!(SparseRle::from_rle(&rle, 0), sparse);
+        assert_eq!(sparse.stored_runs_len(), rle.runs().filter(|run| *run.value != 0).count());
+
+        // another default stores the other runs
+        let other = SparseRle::from_rle(&rle, 1);
+        assert_eq!(RleVec::from(other), rle);
+    }
+
+    #[test]
+    fn merging_runs() {
+        let mut sparse = SparseRle::new(20, 0);
+        sparse.set_range(2..5, 1);
+        sparse.set_range(7..9, 1);
+        assert_eq!(sparse.stored_runs_len(), 2);
+        sparse.set_range(5..7, 1);
+        assert_eq!(sparse.stored_runs().collect::<Vec<_>>(), vec![(2..9, &1)]);
+
+        // splitting a run and filling it again
+        sparse.set(4, 0);
+        assert_eq!(sparse.stored_runs().collect::<Vec<_>>(), vec![(2..4, &1), (5..9, &1)]);
+        sparse.set(4, 1);
+        assert_eq!(sparse.stored_runs_len(), 1);
+
+        sparse.set_range(0..20, 0);
+        assert_eq!(sparse.stored_runs_len(), 0);
+        assert_eq!(sparse, SparseRle::new(20, 0));
+        sparse.set_range(3..3, 5);
+        assert_eq!(sparse.stored_runs_len(), 0);
+
+        let empty: SparseRle<u8> = SparseRle::new(0, 0);
+        assert!(empty.is_empty());
+        assert_eq!(empty.runs().count(), 0);
+        assert!(SparseRle::from_rle(&RleVec::<u8>::new(), 0).is_empty());
+    }
+}
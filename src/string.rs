@@ -0,0 +1,279 @@
+//! A run-length encoded string.
+
+use std::fmt::{self, Write};
+use std::iter::{Copied, FromIterator};
+use std::ops::Range;
+
+use super::{push_slice, Iter, RleVec, Run, Runs};
+
+/// A string storing runs of equal `char`s in an `RleVec`.
+///
+/// Repeated characters like the padding and box drawing of terminal output take one run. The
+/// runs hold whole `char`s, so converting from and to a `&str` is lossless and never splits
+/// the UTF-8 encoding of a character, combining marks are runs of their own.
+///
+/// Like a `String`, the ranges given to [`replace_range`](#method.replace_range) are byte
+/// ranges. [`byte_to_char`](#method.byte_to_char) and [`char_to_byte`](#method.char_to_byte)
+/// convert between byte and `char` indices, which like counting the bytes walk the runs.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleString;
+/// let mut s = RleString::from("┌────────┐");
+/// assert_eq!(s.runs_len(), 3);
+/// assert_eq!(s.char_count(), 10);
+/// assert_eq!(s.byte_len(), 30);
+///
+/// s.replace_range(3..9, " ok ");
+/// assert_eq!(s.to_string(), "┌ ok ──────┐");
+/// assert_eq!(s.char_to_byte(6), Some(10));
+/// assert_eq!(s.byte_to_char(11), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RleString {
+    rle: RleVec<char>,
+}
+
+impl RleString {
+    /// Constructs a new empty `RleString`.
+    pub fn new() -> RleString {
+        RleString { rle: RleVec::new() }
+    }
+
+    /// Returns the number of `char`s.
+    pub fn char_count(&self) -> usize {
+        self.rle.len()
+    }
+
+    /// Returns the length of the UTF-8 encoding in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.rle.runs().map(|Run { len, value }| len * value.len_utf8()).sum()
+    }
+
+    /// Returns `true` if the string contains no characters.
+    pub fn is_empty(&self) -> bool {
+        self.rle.is_empty()
+    }
+
+    /// Returns the number of runs.
+    pub fn runs_len(&self) -> usize {
+        self.rle.runs_len()
+    }
+
+    /// Appends a `char`.
+    pub fn push(&mut self, c: char) {
+        self.rle.push(c)
+    }
+
+    /// Appends the `char`s of a string slice.
+    pub fn push_str(&mut self, s: &str) {
+        self.rle.extend(s.chars())
+    }
+
+    /// Returns an iterator over the `char`s.
+    pub fn chars(&self) -> Copied<Iter<'_, char>> {
+        self.rle.iter().copied()
+    }
+
+    /// Returns an iterator over the runs of `char`s.
+    pub fn runs(&self) -> Runs<'_, char> {
+        self.rle.runs()
+    }
+
+    /// Returns the underlying `RleVec`.
+    pub fn as_rle(&self) -> &RleVec<char> {
+        &self.rle
+    }
+
+    /// Returns the underlying `RleVec`.
+    pub fn into_rle(self) -> RleVec<char> {
+        self.rle
+    }
+
+    /// Returns the `char` index of the character starting at byte `index`, or `None` if
+    /// `index` is not on a `char` boundary. The byte length maps to the `char` count.
+    pub fn byte_to_char(&self, index: usize) -> Option<usize> {
+        let (mut bytes, mut chars) = (0, 0);
+        for Run { len, value } in self.rle.runs() {
+            let width = value.len_utf8();
+            if index < bytes + len * width {
+                let offset = index - bytes;
+                return if offset % width == 0 { Some(chars + offset / width) } else { None }
+            }
+            bytes += len * width;
+            chars += len;
+        }
+        if index == bytes { Some(chars) } else { None }
+    }
+
+    /// Returns the byte index of the `char` at `index`, or `None` if it is out of bounds. The
+    /// `char` count maps to the byte length.
+    pub fn char_to_byte(&self, index: usize) -> Option<usize> {
+        let (mut bytes, mut chars) = (0, 0);
+        for Run { len, value } in self.rle.runs() {
+            if index < chars + len {
+                return Some(bytes + (index - chars) * value.len_utf8())
+            }
+            bytes += len * value.len_utf8();
+            chars += len;
+        }
+        if index == chars { Some(bytes) } else { None }
+    }
+
+    /// Replaces the characters in the byte `range` with `replace_with`.
+    ///
+    /// # Panics
+    /// Panics if the start or end of `range` is not on a `char` boundary or out of bounds.
+    pub fn replace_range(&mut self, range: Range<usize>, replace_with: &str) {
+        let start = self.byte_to_char(range.start)
+            .unwrap_or_else(|| panic!("byte index {} is not a char boundary", range.start));
+        let end = self.byte_to_char(range.end)
+            .unwrap_or_else(|| panic!("byte index {} is not a char boundary", range.end));
+        assert!(start <= end, "range start {} is larger than the end {}", range.start, range.end);
+
+        let mut rle = RleVec::new();
+        push_slice(&mut rle, &self.rle, 0..start);
+        rle.extend(replace_with.chars());
+        push_slice(&mut rle, &self.rle, end..self.rle.len());
+        self.rle = rle;
+    }
+}
+
+impl fmt::Display for RleString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.width().is_some() || f.precision().is_some() {
+            return f.pad(&self.chars().collect::<String>())
+        }
+        for Run { len, value } in self.rle.runs() {
+            for _ in 0..len {
+                f.write_char(*value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a str> for RleString {
+    fn from(s: &'a str) -> RleString {
+        s.chars().collect()
+    }
+}
+
+impl From<RleVec<char>> for RleString {
+    fn from(rle: RleVec<char>) -> RleString {
+        RleString { rle }
+    }
+}
+
+impl From<RleString> for String {
+    fn from(s: RleString) -> String {
+        s.chars().collect()
+    }
+}
+
+impl FromIterator<char> for RleString {
+    fn from_iter<T>(iter: T) -> Self where T: IntoIterator<Item = char> {
+        RleString { rle: iter.into_iter().collect() }
+    }
+}
+
+impl Extend<char> for RleString {
+    fn extend<T>(&mut self, iter: T) where T: IntoIterator<Item = char> {
+        self.rle.extend(iter)
+    }
+}
+
+impl<'a> Extend<&'a str> for RleString {
+    fn extend<T>(&mut self, iter: T) where T: IntoIterator<Item = &'a str> {
+        for s in iter {
+            self.push_str(s);
+        }
+    }
+}
+
+impl PartialEq<str> for RleString {
+    fn eq(&self, other: &str) -> bool {
+        self.chars().eq(other.chars())
+    }
+}
+
+impl<'a> PartialEq<&'a str> for RleString {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.chars().eq(other.chars())
+    }
+}
+
+impl fmt::Write for RleString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::same_as;
+
+    #[test]
+    fn same_as_string() {
+        let alphabet = ['a', ' ', 'é', '─', '\u{301}', '🦀'];
+        let mut string = String::new();
+        let mut rle = RleString::new();
+        same_as(13, 1_000, &mut string, &mut rle, |rng, string, rle| {
+            let piece: String = (0..rng.below(4))
+                .flat_map(|_| std::iter::repeat_n(alphabet[rng.below(alphabet.len())], rng.below(5) + 1))
+                .collect();
+            if rng.below(3) == 0 {
+                string.push_str(&piece);
+                rle.push_str(&piece);
+            } else {
+                let boundaries: Vec<usize> = string.char_indices().map(|(i, _)| i)
+                    .chain(Some(string.len())).collect();
+                let start = boundaries[rng.below(boundaries.len())];
+                let end = start + rng.below(string.len() + 1 - start);
+                let end = *boundaries.iter().find(|&&i| i >= end).unwrap();
+                string.replace_range(start..end, &piece);
+                rle.replace_range(start..end, &piece);
+            }
+        }, |_, rle| assert!(rle.as_rle().is_canonical()));
+        assert_eq!(rle.to_string(), string);
+        assert_eq!(rle, *string.as_str());
+        assert_eq!(rle.byte_len(), string.len());
+        assert_eq!(rle.char_count(), string.chars().count());
+        assert_eq!(RleString::from(string.as_str()), rle);
+
+        for (chars, (bytes, _)) in string.char_indices().enumerate() {
+            assert_eq!(rle.char_to_byte(chars), Some(bytes));
+            assert_eq!(rle.byte_to_char(bytes), Some(chars));
+        }
+        assert_eq!(rle.char_to_byte(rle.char_count()), Some(string.len()));
+        assert_eq!(rle.char_to_byte(rle.char_count() + 1), None);
+        assert_eq!(rle.byte_to_char(string.len()), Some(rle.char_count()));
+        for i in 0..string.len() + 2 {
+            assert_eq!(rle.byte_to_char(i).is_some(), string.is_char_boundary(i));
+        }
+    }
+
+    #[test]
+    fn formatting() {
+        let mut s = RleString::from("ab");
+        write!(s, "{}", 12).unwrap();
+        s.extend(vec!["--", "-"]);
+        assert_eq!(s, "ab12---");
+        assert_eq!(format!("[{:>9}]", s), "[  ab12---]");
+        assert_eq!(format!("{:.3}", s), "ab1");
+        assert_eq!(String::from(s.clone()), "ab12---");
+
+        s.replace_range(0..7, "");
+        assert!(s.is_empty());
+        s.replace_range(0..0, "x");
+        assert_eq!(s.chars().collect::<Vec<_>>(), vec!['x']);
+    }
+
+    #[test]
+    #[should_panic(expected = "byte index 1 is not a char boundary")]
+    fn replace_inside_char() {
+        RleString::from("é").replace_range(1..2, "e");
+    }
+}
@@ -0,0 +1,31 @@
+//! Helpers shared by the tests of the modules.
+
+/// A xorshift generator for reproducible random tests.
+pub struct Rng(u64);
+
+impl Rng {
+    /// Constructs a generator, `seed` must not be zero.
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    /// Returns a number below `n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// Applies `steps` random edits to the structure under test and to the reference it has to
+/// match, `edit` makes the same edit to both and `check` runs after every edit.
+pub fn same_as<R, S>(seed: u64, steps: usize, reference: &mut R, tested: &mut S,
+                     mut edit: impl FnMut(&mut Rng, &mut R, &mut S), mut check: impl FnMut(&R, &S))
+{
+    let mut rng = Rng::new(seed);
+    for _ in 0..steps {
+        edit(&mut rng, reference, tested);
+        check(reference, tested);
+    }
+}
@@ -0,0 +1,257 @@
+//! The text representation of an `RleVec`.
+//!
+//! `Display` writes the runs as a list like `[0×5, 1×3, 7]`, a run of length one is written as
+//! its value only. `FromStr` parses the same format and accepts `*` in place of `×`. Values are
+//! split at commas, so they can not contain a `,`, `×` or `*` themselves.
+//!
+//! `Debug` writes the length and the runs in the same compact form, eliding the middle runs of
+//! large vectors.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{RleIndex, RleVec, Run};
+
+const TIMES: char = '×';
+// the runs shown by Debug before and after the elided runs
+const DEBUG_HEAD: usize = 16;
+const DEBUG_TAIL: usize = 4;
+
+/// The error returned when parsing an `RleVec` from a string fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRleError<E> {
+    /// The string does not start with `[` and end with `]`.
+    MissingBrackets,
+    /// The length of the run at this position is not a positive integer.
+    InvalidLength {
+        /// The position of the run in the list.
+        run: usize,
+    },
+    /// The value of the run at this position could not be parsed.
+    InvalidValue {
+        /// The position of the run in the list.
+        run: usize,
+        /// The error returned by the value parser.
+        error: E,
+    },
+    /// The length overflows the index type.
+    IndexOverflow,
+}
+
+impl<E: fmt::Display> fmt::Display for ParseRleError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseRleError::MissingBrackets => write!(f, "the runs are not enclosed in brackets"),
+            ParseRleError::InvalidLength { run } => write!(f, "run {} has an invalid length", run),
+            ParseRleError::InvalidValue { run, ref error } =>
+                write!(f, "run {} has an invalid value: {}", run, error),
+            ParseRleError::IndexOverflow => write!(f, "the length of the RleVec overflows its index type"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ParseRleError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ParseRleError::InvalidValue { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a run as `value×len`, or `value` for a run of length one.
+///
+/// # Example
+/// ```
+/// # use rle_vec::Run;
+/// assert_eq!(Run { len: 3, value: 1.5 }.to_string(), "1.5×3");
+/// assert_eq!(format!("{:.1}", Run { len: 1, value: 2.25 }), "2.2");
+/// ```
+impl<T: fmt::Display> fmt::Display for Run<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)?;
+        if self.len != 1 {
+            write!(f, "{}{}", TIMES, self.len)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the runs as `[value×len, ...]`, the formatting options apply to every value.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleVec;
+/// let rle = RleVec::from(&[0, 0, 0, 0, 0, 1, 1, 1, 7][..]);
+/// assert_eq!(rle.to_string(), "[0×5, 1×3, 7]");
+///
+/// let rle: RleVec<f64> = RleVec::from_run_lengths(&[2, 1], vec![0.5, 0.25]).unwrap();
+/// assert_eq!(format!("{:.2}", rle), "[0.50×2, 0.25]");
+/// ```
+impl<T: fmt::Display, I: RleIndex> fmt::Display for RleVec<T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, run) in self.runs().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            fmt::Display::fmt(&run, f)?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Parses the runs written by `Display`, neighbouring runs with equal values are merged.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, ParseRleError};
+/// let rle: RleVec<u8> = "[0×5, 1*3, 7]".parse().unwrap();
+/// assert_eq!(rle.to_vec(), vec![0, 0, 0, 0, 0, 1, 1, 1, 7]);
+///
+/// assert_eq!("[1×0]".parse::<RleVec<u8>>(), Err(ParseRleError::InvalidLength { run: 0 }));
+/// ```
+impl<T: FromStr + Eq, I: RleIndex> FromStr for RleVec<T, I> {
+    type Err = ParseRleError<T::Err>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let runs = s.trim().strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or(ParseRleError::MissingBrackets)?;
+
+        let mut rle = RleVec::default();
+        if runs.trim().is_empty() {
+            return Ok(rle)
+        }
+        for (run, item) in runs.split(',').enumerate() {
+            let (value, len) = match item.rfind([TIMES, '*']) {
+                Some(i) => {
+                    let len = item[i..].chars().skip(1).collect::<String>();
+                    match len.trim().parse() {
+                        Ok(len) if len > 0 => (&item[..i], len),
+                        _ => return Err(ParseRleError::InvalidLength { run }),
+                    }
+                }
+                None => (item, 1),
+            };
+            let value = value.trim().parse().map_err(|error| ParseRleError::InvalidValue { run, error })?;
+            rle.try_push_n(len, value).map_err(|_| ParseRleError::IndexOverflow)?;
+        }
+        Ok(rle)
+    }
+}
+
+struct DebugRun<'a, T: 'a> {
+    len: usize,
+    value: &'a T,
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for DebugRun<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.value, f)?;
+        write!(f, "{}{}", TIMES, self.len)
+    }
+}
+
+struct Elided(usize);
+
+impl fmt::Debug for Elided {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "...{} run{}...", self.0, if self.0 == 1 { "" } else { "s" })
+    }
+}
+
+struct DebugRuns<'a, T: 'a, I: 'a>(&'a RleVec<T, I>);
+
+impl<'a, T: fmt::Debug, I: RleIndex> fmt::Debug for DebugRuns<'a, T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let runs = self.0.runs().map(|run| DebugRun { len: run.len, value: run.value });
+        let len = self.0.runs_len();
+        if len <= DEBUG_HEAD + DEBUG_TAIL {
+            return f.debug_list().entries(runs).finish()
+        }
+        let elided = len - DEBUG_HEAD - DEBUG_TAIL;
+        let mut list = f.debug_list();
+        let mut runs = runs;
+        list.entries(runs.by_ref().take(DEBUG_HEAD));
+        list.entry(&Elided(elided));
+        list.entries(runs.skip(elided)).finish()
+    }
+}
+
+/// Shows the length and the runs as `value×len`, only the first 16 and last 4 runs of larger
+/// vectors are shown.
+///
+/// # Example
+/// ```
+/// # use rle_vec::RleVec;
+/// let rle = RleVec::from(&["a", "a", "b"][..]);
+/// assert_eq!(format!("{:?}", rle), r#"RleVec { len: 3, runs: ["a"×2, "b"×1] }"#);
+///
+/// let rle: RleVec<u32> = (0..100).collect();
+/// assert!(format!("{:?}", rle).contains("15×1, ...80 runs..., 96×1"));
+/// ```
+impl<T: fmt::Debug, I: RleIndex> fmt::Debug for RleVec<T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RleVec")
+            .field("len", &self.len())
+            .field("runs", &DebugRuns(self))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_roundtrips() {
+        let v = vec![3, 3, 3, 0, 1, 1, 7, 7, 7, 7];
+        let rle: RleVec<i32, u16> = v.iter().cloned().collect();
+        let text = rle.to_string();
+        assert_eq!(text, "[3×3, 0, 1×2, 7×4]");
+        assert_eq!(text.parse::<RleVec<i32, u16>>().unwrap(), rle);
+        assert_eq!(RleVec::<i32>::new().to_string(), "[]");
+        assert_eq!(" [ ] ".parse::<RleVec<i32>>().unwrap(), RleVec::new());
+
+        // whitespace, ascii multiplication and unmerged runs
+        let parsed: RleVec<i32> = " [ 3 * 2,3, 0 ×1 ,1×2,7 ×4 ]".parse().unwrap();
+        assert_eq!(parsed.to_vec(), v);
+        assert_eq!(parsed.runs_len(), 4);
+
+        let words: RleVec<String> = "[to×2, be, or, not]".parse().unwrap();
+        assert_eq!(words.to_string(), "[to×2, be, or, not]");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("0×5".parse::<RleVec<u8, u16>>(), Err(ParseRleError::MissingBrackets));
+        assert_eq!("[0×5".parse::<RleVec<u8, u16>>(), Err(ParseRleError::MissingBrackets));
+        assert_eq!("[0×5, 1×]".parse::<RleVec<u8, u16>>(), Err(ParseRleError::InvalidLength { run: 1 }));
+        assert_eq!("[0×-1]".parse::<RleVec<u8, u16>>(), Err(ParseRleError::InvalidLength { run: 0 }));
+        assert_eq!("[0×70000]".parse::<RleVec<u8, u16>>(), Err(ParseRleError::IndexOverflow));
+        assert!(matches!("[0, , 1]".parse::<RleVec<u8, u16>>(), Err(ParseRleError::InvalidValue { run: 1, .. })));
+
+        let error = "[1, 300×2]".parse::<RleVec<u8, u16>>().unwrap_err();
+        assert!(matches!(error, ParseRleError::InvalidValue { run: 1, .. }));
+        assert_eq!(error.to_string(), "run 1 has an invalid value: number too large to fit in target type");
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn debug_elision() {
+        let rle: RleVec<u8> = RleVec::new();
+        assert_eq!(format!("{:?}", rle), "RleVec { len: 0, runs: [] }");
+
+        let rle: RleVec<u32, u32> = (0..20).flat_map(|i| vec![i; 2]).collect();
+        let debug = format!("{:?}", rle);
+        assert!(!debug.contains("..."));
+        assert!(debug.starts_with("RleVec { len: 40, runs: [0×2, 1×2,"));
+
+        let rle: RleVec<u32, u32> = (0..21).flat_map(|i| vec![i; 2]).collect();
+        let debug = format!("{:?}", rle);
+        assert!(debug.ends_with("15×2, ...1 run..., 17×2, 18×2, 19×2, 20×2] }"));
+        assert_eq!(format!("{:#?}", rle).lines().count(), 26);
+    }
+}
@@ -0,0 +1,284 @@
+//! The run-length encoding of TGA images.
+//!
+//! The image data is a sequence of packets, each starting with a header byte. If its high bit
+//! is set the packet is a run of `(header & 0x7f) + 1` copies of the one pixel that follows,
+//! otherwise `header + 1` raw pixels follow.
+//!
+//! Pixels are stored as in the file, `[u8; 3]` holds blue, green and red and `[u8; 4]` adds
+//! alpha. The encoder never lets a packet cross a scanline, the decoder accepts packets that do,
+//! as written by some older encoders.
+//!
+//! # Example
+//! ```
+//! # use rle_vec::{RleVec, tga};
+//! let red = [0, 0, 255];
+//! let blue = [255, 0, 0];
+//! let rows = vec![RleVec::from(&[red, red, red, blue][..])];
+//!
+//! let data = tga::encode(&rows);
+//! assert_eq!(data, vec![0x82, 0, 0, 255, 0x00, 255, 0, 0]);
+//! assert_eq!(tga::decode(&data, 4, 1).unwrap(), rows);
+//! ```
+
+use std::cmp;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use super::{RleIndex, RleVec};
+
+// the longest run or raw packet
+const MAX_PACKET: usize = 128;
+const RUN_PACKET: u8 = 0x80;
+
+/// A pixel of a TGA image, stored as `SIZE` bytes.
+pub trait TgaPixel: Copy + Eq {
+    /// The number of bytes of a pixel.
+    const SIZE: usize;
+
+    /// Reads a pixel from exactly `SIZE` bytes.
+    fn from_bytes(bytes: &[u8]) -> Self;
+
+    /// Appends the bytes of the pixel to `out`.
+    fn write_bytes(&self, out: &mut Vec<u8>);
+}
+
+impl TgaPixel for u8 {
+    const SIZE: usize = 1;
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+macro_rules! impl_tga_pixel {
+    ($($size:expr),*) => {$(
+        impl TgaPixel for [u8; $size] {
+            const SIZE: usize = $size;
+
+            fn from_bytes(bytes: &[u8]) -> Self {
+                <[u8; $size]>::try_from(bytes).expect("pixel bytes")
+            }
+
+            fn write_bytes(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(self);
+            }
+        }
+    )*}
+}
+
+impl_tga_pixel!(2, 3, 4);
+
+/// The error returned when decoding malformed TGA image data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The packet at this offset is cut off by the end of the data.
+    Truncated {
+        /// The offset of the packet.
+        offset: usize,
+    },
+    /// The packet at this offset holds more pixels than are left in the image.
+    TooManyPixels {
+        /// The offset of the packet.
+        offset: usize,
+    },
+    /// The number of pixels, `width * height`, overflows `usize`.
+    SizeOverflow,
+    /// Allocating the rows of the image failed.
+    TooLarge {
+        /// The number of rows.
+        height: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated { offset } =>
+                write!(f, "the packet at offset {} is truncated", offset),
+            DecodeError::TooManyPixels { offset } =>
+                write!(f, "the packet at offset {} extends past the end of the image", offset),
+            DecodeError::SizeOverflow => write!(f, "the number of pixels overflows usize"),
+            DecodeError::TooLarge { height } =>
+                write!(f, "the {} rows of the image can not be allocated", height),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Encodes the rows of an image as TGA run-length packets.
+///
+/// Runs of two or more pixels become run packets and single pixels are collected in raw
+/// packets.
+pub fn encode<P: TgaPixel, I: RleIndex>(rows: &[RleVec<P, I>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut raw = Vec::new();
+    for row in rows {
+        for run in row.runs() {
+            if run.len == 1 {
+                raw.push(*run.value);
+                if raw.len() == MAX_PACKET {
+                    flush_raw(&mut raw, &mut out);
+                }
+                continue
+            }
+
+            flush_raw(&mut raw, &mut out);
+            let mut len = run.len;
+            while len > 1 {
+                let n = cmp::min(len, MAX_PACKET);
+                out.push(RUN_PACKET | (n - 1) as u8);
+                run.value.write_bytes(&mut out);
+                len -= n;
+            }
+            if len == 1 {
+                raw.push(*run.value);
+            }
+        }
+        flush_raw(&mut raw, &mut out);
+    }
+    out
+}
+
+fn flush_raw<P: TgaPixel>(raw: &mut Vec<P>, out: &mut Vec<u8>) {
+    if !raw.is_empty() {
+        out.push((raw.len() - 1) as u8);
+        for pixel in raw.iter() {
+            pixel.write_bytes(out);
+        }
+        raw.clear();
+    }
+}
+
+/// Decodes the run-length packets of an image of `height` rows of `width` pixels.
+///
+/// Decoding stops after the last pixel, the bytes that follow, like the TGA footer, are
+/// ignored.
+///
+/// # Panics
+/// Panics if `width` overflows the index type.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleVec, tga};
+/// // a run packet of five pixels that continues on the second row
+/// let rows: Vec<RleVec<u8>> = tga::decode(&[0x84, 9, 0x00, 7], 3, 2).unwrap();
+/// assert_eq!(rows[0].to_vec(), vec![9, 9, 9]);
+/// assert_eq!(rows[1].to_vec(), vec![9, 9, 7]);
+///
+/// assert_eq!(tga::decode::<u8, usize>(&[0x86, 9], 3, 2),
+///            Err(tga::DecodeError::TooManyPixels { offset: 0 }));
+/// ```
+pub fn decode<P: TgaPixel, I: RleIndex>(data: &[u8], width: usize, height: usize)
+    -> Result<Vec<RleVec<P, I>>, DecodeError>
+{
+    let mut remaining = width.checked_mul(height).ok_or(DecodeError::SizeOverflow)?;
+    // a row is allocated when its first pixel is decoded
+    let mut rows: Vec<RleVec<P, I>> = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let mut offset = 0;
+    while remaining > 0 {
+        let packet = offset;
+        let header = *data.get(offset).ok_or(DecodeError::Truncated { offset })?;
+        let count = (header & !RUN_PACKET) as usize + 1;
+        if count > remaining {
+            return Err(DecodeError::TooManyPixels { offset: packet })
+        }
+        let size = if header & RUN_PACKET != 0 { P::SIZE } else { count * P::SIZE };
+        let bytes = data.get(offset + 1..offset + 1 + size).ok_or(DecodeError::Truncated { offset: packet })?;
+        offset += 1 + size;
+        remaining -= count;
+
+        if header & RUN_PACKET != 0 {
+            let pixel = P::from_bytes(bytes);
+            let mut count = count;
+            while count > 0 {
+                let n = cmp::min(count, width - x);
+                if x == 0 {
+                    rows.push(RleVec::default());
+                }
+                rows[y].push_n(n, pixel);
+                count -= n;
+                x += n;
+                if x == width {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        } else {
+            for pixel in bytes.chunks(P::SIZE) {
+                if x == 0 {
+                    rows.push(RleVec::default());
+                }
+                rows[y].push(P::from_bytes(pixel));
+                x += 1;
+                if x == width {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+    // only the empty rows of an image without columns are left
+    rows.try_reserve_exact(height - rows.len()).map_err(|_| DecodeError::TooLarge { height })?;
+    rows.resize_with(height, RleVec::default);
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets() {
+        let rows: Vec<RleVec<[u8; 4]>> = vec![
+            RleVec::from(&[[1, 2, 3, 4]; 129][..]),
+            (0..129u8).map(|i| [i, i, i, 255]).collect(),
+        ];
+        let data = encode(&rows);
+        // a run of 128, a raw packet of the remaining pixel and raw packets of 128 and 1
+        assert_eq!(data.len(), 2 * 5 + 2 + 129 * 4);
+        assert_eq!(&data[..7], &[0xff, 1, 2, 3, 4, 0x00, 1]);
+        assert_eq!(data[10], 0x7f);
+        assert_eq!(decode::<_, usize>(&data, 129, 2).unwrap(), rows);
+
+        let gray: Vec<RleVec<u8, u32>> = vec![
+            vec![0, 0, 1, 2, 2, 2].into_iter().collect(),
+            vec![3, 4, 5, 5, 5, 5].into_iter().collect(),
+        ];
+        let data = encode(&gray);
+        assert_eq!(data, vec![0x81, 0, 0x00, 1, 0x82, 2, 0x01, 3, 4, 0x83, 5]);
+        assert_eq!(decode(&data, 6, 2).unwrap(), gray);
+
+        let rgb: Vec<RleVec<[u8; 3]>> = vec![RleVec::from(&[[1, 2, 3], [1, 2, 3], [4, 5, 6]][..])];
+        assert_eq!(decode(&encode(&rgb), 3, 1).unwrap(), rgb);
+        assert!(decode::<[u8; 3], usize>(&[], 0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_data() {
+        let data = [0x81, 0, 0x02, 1, 2, 3];
+        for end in 0..data.len() {
+            let offset = if end < 2 { 0 } else { 2 };
+            assert_eq!(decode::<u8, usize>(&data[..end], 5, 1), Err(DecodeError::Truncated { offset }));
+        }
+        assert_eq!(decode::<u8, usize>(&data, 4, 1), Err(DecodeError::TooManyPixels { offset: 2 }));
+        assert_eq!(decode::<u8, usize>(&data, usize::MAX, 2), Err(DecodeError::SizeOverflow));
+
+        // the rows of a corrupt height are not allocated before the data is decoded
+        assert_eq!(decode::<u8, usize>(&[0x81], 2, 1 << 31), Err(DecodeError::Truncated { offset: 0 }));
+        assert_eq!(decode::<u8, usize>(&[], 0, usize::MAX / 2),
+                   Err(DecodeError::TooLarge { height: usize::MAX / 2 }));
+        assert_eq!(decode::<u8, usize>(&[], 0, 3).unwrap(), vec![RleVec::new(); 3]);
+
+        let rows = decode::<u8, usize>(&data, 5, 1).unwrap();
+        assert_eq!(rows[0].to_vec(), vec![0, 0, 1, 2, 3]);
+        // trailing bytes are ignored
+        assert_eq!(decode::<u8, usize>(&[0x81, 0, 0xff], 2, 1).unwrap(), vec![RleVec::from(&[0u8, 0][..])]);
+    }
+}
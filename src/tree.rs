@@ -0,0 +1,923 @@
+//! A run-length encoded vector backed by a balanced tree of runs.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::iter::repeat_n;
+use std::ops::{Bound, Index, RangeBounds};
+
+use super::{RleIndex, RleVec, Run};
+
+type Tree<T> = Option<Box<Node<T>>>;
+
+struct Node<T> {
+    value: T,
+    len: usize,
+    // the number of elements and the number of runs in this subtree
+    size: usize,
+    runs: usize,
+    priority: u64,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+impl<T> Node<T> {
+    fn new(len: usize, value: T, priority: u64) -> Box<Node<T>> {
+        Box::new(Node { value, len, size: len, runs: 1, priority, left: None, right: None })
+    }
+
+    fn update(&mut self) {
+        self.size = self.len + size(&self.left) + size(&self.right);
+        self.runs = 1 + runs(&self.left) + runs(&self.right);
+    }
+}
+
+impl<T: Clone> Clone for Node<T> {
+    fn clone(&self) -> Self {
+        Node {
+            value: self.value.clone(),
+            len: self.len,
+            size: self.size,
+            runs: self.runs,
+            priority: self.priority,
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+fn size<T>(tree: &Tree<T>) -> usize {
+    tree.as_ref().map_or(0, |node| node.size)
+}
+
+fn runs<T>(tree: &Tree<T>) -> usize {
+    tree.as_ref().map_or(0, |node| node.runs)
+}
+
+/// Joins two trees, all elements of `left` come before the elements of `right`.
+fn merge<T>(left: Tree<T>, right: Tree<T>) -> Tree<T> {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority >= right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                left.update();
+                Some(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                right.update();
+                Some(right)
+            }
+        }
+    }
+}
+
+/// Splits a tree into the first `at` elements and the rest, a run containing position `at` is
+/// broken in two.
+fn split<T: Clone>(tree: Tree<T>, at: usize, seed: &mut u64) -> (Tree<T>, Tree<T>) {
+    let mut node = match tree {
+        Some(node) => node,
+        None => return (None, None),
+    };
+
+    let left_size = size(&node.left);
+    if at <= left_size {
+        let (left, right) = split(node.left.take(), at, seed);
+        node.left = right;
+        node.update();
+        (left, Some(node))
+    } else if at >= left_size + node.len {
+        let (left, right) = split(node.right.take(), at - left_size - node.len, seed);
+        node.right = left;
+        node.update();
+        (Some(node), right)
+    } else {
+        // The second half needs its own priority, repeatedly splitting a run into nodes with
+        // equal priorities would degrade the tree into a list.
+        let offset = at - left_size;
+        let second = Node::new(node.len - offset, node.value.clone(), next_priority(seed));
+        let right = merge(Some(second), node.right.take());
+        node.len = offset;
+        node.update();
+        (Some(node), right)
+    }
+}
+
+fn first<T>(tree: &Tree<T>) -> Option<&Node<T>> {
+    let mut node = tree.as_ref()?;
+    while let Some(ref left) = node.left {
+        node = left;
+    }
+    Some(node)
+}
+
+fn last<T>(tree: &Tree<T>) -> Option<&Node<T>> {
+    let mut node = tree.as_ref()?;
+    while let Some(ref right) = node.right {
+        node = right;
+    }
+    Some(node)
+}
+
+/// Removes the first run of a non empty tree.
+fn pop_first<T>(mut node: Box<Node<T>>) -> (Box<Node<T>>, Tree<T>) {
+    match node.left.take() {
+        Some(left) => {
+            let (first, rest) = pop_first(left);
+            node.left = rest;
+            node.update();
+            (first, Some(node))
+        }
+        None => {
+            let rest = node.right.take();
+            (node, rest)
+        }
+    }
+}
+
+/// Lengthens the last run of a non empty tree by `n`.
+fn extend_last<T>(node: &mut Node<T>, n: usize) {
+    node.size += n;
+    match node.right {
+        Some(ref mut right) => extend_last(right, n),
+        None => node.len += n,
+    }
+}
+
+/// Joins two trees and merges the runs at the seam when their values are equal.
+fn concat<T: PartialEq>(left: Tree<T>, right: Tree<T>) -> Tree<T> {
+    let equal = match (last(&left), first(&right)) {
+        (Some(l), Some(r)) => l.value == r.value,
+        _ => false,
+    };
+
+    if !equal {
+        return merge(left, right);
+    }
+
+    let mut left = left.unwrap();
+    let (first, rest) = pop_first(right.unwrap());
+    extend_last(&mut left, first.len);
+    merge(Some(left), rest)
+}
+
+/// Returns the node containing `index`.
+fn find<T>(tree: &Tree<T>, mut index: usize) -> Option<&Node<T>> {
+    let mut node = tree.as_ref()?;
+    loop {
+        let left_size = size(&node.left);
+        if index < left_size {
+            node = node.left.as_ref()?;
+        } else if index < left_size + node.len {
+            return Some(node);
+        } else {
+            index -= left_size + node.len;
+            node = node.right.as_ref()?;
+        }
+    }
+}
+
+/// A vector of runs stored in a balanced binary tree, keyed by the position of the runs.
+///
+/// `RleTree` offers the same interface as [`RleVec`](struct.RleVec.html), but every node of
+/// the tree stores the number of elements in its subtree. No run positions are stored, so
+/// breaking a run, inserting or removing an element only touches the nodes on a single path
+/// from the root and never shifts the positions of the following runs. The tree is a treap,
+/// which stays balanced in expectation.
+///
+/// |         |push|index|set|insert|remove|splice|
+/// |---------|----|-----|---|------|------|------|
+/// |`RleVec` |O(1)|O(log&nbsp;n)|O((log&nbsp;n)&nbsp;+&nbsp;2n)|O((log&nbsp;n)&nbsp;+&nbsp;2n)|O((log&nbsp;n)&nbsp;+&nbsp;n)| |
+/// |`RleTree`|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|O(log&nbsp;n)|
+///
+/// where n is the number of runs. The price is a larger memory footprint per run and slower
+/// iteration, so `RleVec` remains the better choice for data that is mostly appended and read.
+///
+/// # Example
+/// ```
+/// # use rle_vec::{RleTree, Run};
+/// let mut tree = RleTree::from(&[0, 0, 0, 0, 1, 1][..]);
+///
+/// tree.insert(2, 5);
+/// tree.set(0, 1);
+/// assert_eq!(tree.remove(6), 1);
+///
+/// assert_eq!(tree.to_vec(), vec![1, 0, 5, 0, 0, 1]);
+/// assert_eq!(tree.runs_len(), 5);
+///
+/// let removed = tree.splice(1..4, vec![1, 1]);
+/// assert_eq!(removed.to_vec(), vec![0, 5, 0]);
+/// assert_eq!(tree.runs().collect::<Vec<_>>(), vec![Run { len: 3, value: &1 },
+///                                                  Run { len: 1, value: &0 },
+///                                                  Run { len: 1, value: &1 }]);
+/// ```
+pub struct RleTree<T> {
+    root: Tree<T>,
+    seed: u64,
+}
+
+// xorshift64 state, any non-zero value works
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+fn next_priority(seed: &mut u64) -> u64 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    *seed
+}
+
+impl<T> RleTree<T> {
+    /// Constructs a new empty `RleTree<T>`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleTree;
+    /// let tree = RleTree::<i32>::new();
+    /// assert!(tree.is_empty());
+    /// ```
+    pub fn new() -> RleTree<T> {
+        RleTree { root: None, seed: SEED }
+    }
+
+    /// Returns the number of elements in the tree.
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Returns `true` if the tree contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Clears the tree, removing all values.
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    /// Returns the number of runs.
+    pub fn runs_len(&self) -> usize {
+        runs(&self.root)
+    }
+
+    /// Returns the first value, or None if it is empty.
+    pub fn first(&self) -> Option<&T> {
+        first(&self.root).map(|node| &node.value)
+    }
+
+    /// Returns the last value, or None if it is empty.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleTree;
+    /// let tree = RleTree::from(&[10, 10, 40, 40, 30][..]);
+    /// assert_eq!(tree.last(), Some(&30));
+    /// ```
+    pub fn last(&self) -> Option<&T> {
+        last(&self.root).map(|node| &node.value)
+    }
+
+    /// Returns the last run, or None if it is empty.
+    pub fn last_run(&self) -> Option<Run<&T>> {
+        last(&self.root).map(|node| Run { len: node.len, value: &node.value })
+    }
+
+    /// Returns a reference to the value at `index`, or None if out of bounds.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleTree;
+    /// let tree = RleTree::from(&[10, 10, 40][..]);
+    /// assert_eq!(tree.get(2), Some(&40));
+    /// assert_eq!(tree.get(3), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<&T> {
+        find(&self.root, index).map(|node| &node.value)
+    }
+
+    /// Returns the 0-based start coordinates of the runs
+    pub fn starts(&self) -> Vec<usize> {
+        let mut start = 0;
+        self.runs().map(|run| { start += run.len; start - run.len }).collect()
+    }
+
+    /// Returns the 0-based end coordinates of the runs
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleTree;
+    /// let tree = RleTree::from(&[1, 1, 2, 2, 3][..]);
+    ///
+    /// assert_eq!(tree.starts(), vec![0, 2, 4]);
+    /// assert_eq!(tree.ends(), vec![1, 3, 4]);
+    /// ```
+    pub fn ends(&self) -> Vec<usize> {
+        let mut end = 0;
+        self.runs().map(|run| { end += run.len; end - 1 }).collect()
+    }
+
+    /// Returns an iterator over values.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            root: &self.root,
+            front: Position::at_start(&self.root),
+            back: Position::at_end(&self.root),
+            index: 0,
+            index_back: self.len(),
+        }
+    }
+
+    /// Returns an iterator over the runs.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::{RleTree, Run};
+    /// let tree = RleTree::from(&[1, 1, 2][..]);
+    ///
+    /// let mut iterator = tree.runs();
+    /// assert_eq!(iterator.next(), Some(Run{ len: 2, value: &1 }));
+    /// assert_eq!(iterator.next(), Some(Run{ len: 1, value: &2 }));
+    /// assert_eq!(iterator.next(), None);
+    /// ```
+    pub fn runs(&self) -> Runs<'_, T> {
+        Runs { position: Position::at_start(&self.root), remaining: self.runs_len() }
+    }
+
+    fn single(&mut self, n: usize, value: T) -> Tree<T> {
+        if n == 0 { return None }
+        Some(Node::new(n, value, next_priority(&mut self.seed)))
+    }
+}
+
+impl<T: Eq> RleTree<T> {
+    /// Appends an element to the back of the tree.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.push_n(1, value);
+    }
+
+    /// Appends the same element n times to the back of the tree.
+    ///
+    /// # Panics
+    /// Panics if the number of elements overflows a usize.
+    pub fn push_n(&mut self, n: usize, value: T) {
+        if n == 0 { return }
+        assert!(self.len().checked_add(n).is_some(), "length overflows a usize");
+        let single = self.single(n, value);
+        self.root = concat(self.root.take(), single);
+    }
+
+    /// Moves all elements of `other` to the back of this tree, leaving `other` empty.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleTree;
+    /// let mut tree = RleTree::from(&[1, 1, 2][..]);
+    /// let mut other = RleTree::from(&[2, 3][..]);
+    ///
+    /// tree.append(&mut other);
+    /// assert_eq!(tree.runs_len(), 3);
+    /// assert!(other.is_empty());
+    /// ```
+    pub fn append(&mut self, other: &mut RleTree<T>) {
+        self.root = concat(self.root.take(), other.root.take());
+    }
+}
+
+impl<T: Clone> RleTree<T> {
+    /// Construct a `Vec<T>` from this `RleTree`.
+    pub fn to_vec(&self) -> Vec<T> {
+        let mut res = Vec::with_capacity(self.len());
+        for run in self.runs() {
+            res.extend(repeat_n(run.value.clone(), run.len));
+        }
+        res
+    }
+
+    /// Splits the tree in two at the given index, returns the elements from `at` onwards.
+    ///
+    /// # Panics
+    /// Panics if `at > len`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleTree;
+    /// let mut tree = RleTree::from(&[1, 1, 1, 2][..]);
+    /// let tail = tree.split_off(1);
+    ///
+    /// assert_eq!(tree.to_vec(), vec![1]);
+    /// assert_eq!(tail.to_vec(), vec![1, 1, 2]);
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> RleTree<T> {
+        assert!(at <= self.len(), "`at` split index (is {}) should be <= len (is {})", at, self.len());
+        let (left, right) = split(self.root.take(), at, &mut self.seed);
+        self.root = left;
+        RleTree { root: right, seed: next_priority(&mut self.seed) }
+    }
+}
+
+impl<T: Eq + Clone> RleTree<T> {
+    /// Modify the value at given index.
+    ///
+    /// # Panics
+    /// Panics if index is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) {
+        if self[index] == value { return }
+        let (left, rest) = split(self.root.take(), index, &mut self.seed);
+        let (_, right) = split(rest, 1, &mut self.seed);
+        let single = self.single(1, value);
+        self.root = concat(concat(left, single), right);
+    }
+
+    /// Removes and returns the element at position index, shifting all elements after it to the left.
+    ///
+    /// # Panics
+    /// Panics if index is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len(), "index out of bounds: the len is {} but the index is {}", self.len(), index);
+        let (left, rest) = split(self.root.take(), index, &mut self.seed);
+        let (removed, right) = split(rest, 1, &mut self.seed);
+        self.root = concat(left, right);
+        removed.unwrap().value
+    }
+
+    /// Insert a value at the given index.
+    ///
+    /// # Panics
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) {
+        self.insert_n(index, 1, value);
+    }
+
+    /// Insert the same value n times at the given index.
+    ///
+    /// # Panics
+    /// Panics if `index > len` or if the number of elements overflows a usize.
+    ///
+    /// # Example
+    /// ```
+    /// # use rle_vec::RleTree;
+    /// let mut tree = RleTree::from(&[1, 1, 2][..]);
+    /// tree.insert_n(1, 3, 9);
+    ///
+    /// assert_eq!(tree.to_vec(), vec![1, 9, 9, 9, 1, 2]);
+    /// ```
+    pub fn insert_n(&mut self, index: usize, n: usize, value: T) {
+        assert!(index <= self.len(), "insertion index (is {}) should be <= len (is {})", index, self.len());
+        assert!(self.len().checked_add(n).is_some(), "length overflows a usize");
+        let (left, right) = split(self.root.take(), index, &mut self.seed);
+        let single = self.single(n, value);
+        self.root = concat(concat(left, single), right);
+    }
+
+    /// Replaces the elements in `range` with the values of `replace_with` and returns the
+    /// removed elements.
+    ///
+    /// The complexity is **O(log n + m log m)** where m is the number of runs in `replace_with`.
+    ///
+    /// # Panics
+    /// Panics if the start of the range is greater than the end or if the end is out of bounds.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> RleTree<T>
+        where R: RangeBounds<usize>, I: IntoIterator<Item=T>
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(start <= end, "slice index starts at {} but ends at {}", start, end);
+        assert!(end <= self.len(), "range end index {} out of range for length {}", end, self.len());
+
+        let mut replacement = RleTree { root: None, seed: next_priority(&mut self.seed) };
+        replacement.extend(replace_with);
+
+        let (left, rest) = split(self.root.take(), start, &mut self.seed);
+        let (removed, right) = split(rest, end - start, &mut self.seed);
+        self.root = concat(concat(left, replacement.root.take()), right);
+        RleTree { root: removed, seed: replacement.seed }
+    }
+}
+
+impl<T> Index<usize> for RleTree<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index out of bounds: the len is {} but the index is {}", self.len(), index),
+        }
+    }
+}
+
+impl<T> Default for RleTree<T> {
+    fn default() -> Self {
+        RleTree::new()
+    }
+}
+
+impl<T: Clone> Clone for RleTree<T> {
+    fn clone(&self) -> Self {
+        RleTree { root: self.root.clone(), seed: self.seed }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RleTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RleTree").field("runs", &self.runs().collect::<Vec<_>>()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for RleTree<T> {
+    fn eq(&self, other: &RleTree<T>) -> bool {
+        // the shape of the trees depends on the order of the modifications
+        self.len() == other.len() && self.runs().eq(other.runs())
+    }
+}
+
+impl<T: Eq> Eq for RleTree<T> {}
+
+impl<T: Hash> Hash for RleTree<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.runs_len().hash(state);
+        for run in self.runs() {
+            run.hash(state);
+        }
+    }
+}
+
+impl<T: Clone> From<RleTree<T>> for Vec<T> {
+    fn from(tree: RleTree<T>) -> Vec<T> {
+        tree.to_vec()
+    }
+}
+
+impl<'a, T: Eq + Clone> From<&'a [T]> for RleTree<T> {
+    fn from(slice: &'a [T]) -> Self {
+        slice.iter().cloned().collect()
+    }
+}
+
+impl<T: Eq + Clone, I: RleIndex> From<RleVec<T, I>> for RleTree<T> {
+    fn from(rle: RleVec<T, I>) -> Self {
+        rle.runs().map(|run| Run { len: run.len, value: run.value.clone() }).collect()
+    }
+}
+
+impl<T: Eq + Clone> From<RleTree<T>> for RleVec<T> {
+    fn from(tree: RleTree<T>) -> Self {
+        tree.runs().map(|run| Run { len: run.len, value: run.value.clone() }).collect()
+    }
+}
+
+impl<T: Eq> FromIterator<T> for RleTree<T> {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=T> {
+        let mut tree = RleTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Eq> FromIterator<Run<T>> for RleTree<T> {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=Run<T>> {
+        let mut tree = RleTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Eq> Extend<T> for RleTree<T> {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=T> {
+        // collect the runs first, the tree is only walked once per run
+        let mut current: Option<Run<T>> = None;
+        for value in iter {
+            match current {
+                Some(ref mut run) if run.value == value => run.len += 1,
+                _ => {
+                    if let Some(Run { len, value }) = current.take() {
+                        self.push_n(len, value);
+                    }
+                    current = Some(Run { len: 1, value });
+                }
+            }
+        }
+        if let Some(Run { len, value }) = current {
+            self.push_n(len, value);
+        }
+    }
+}
+
+impl<T: Eq> Extend<Run<T>> for RleTree<T> {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=Run<T>> {
+        for Run { len, value } in iter {
+            self.push_n(len, value);
+        }
+    }
+}
+
+impl<'a, T: 'a> IntoIterator for &'a RleTree<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A position in the in-order traversal of the tree.
+///
+/// `pending` holds the ancestors that still have to be visited in the direction of travel.
+struct Position<'a, T: 'a> {
+    pending: Vec<&'a Node<T>>,
+    current: Option<&'a Node<T>>,
+    // the number of elements of the current run that have been passed
+    offset: usize,
+}
+
+impl<'a, T: 'a> Position<'a, T> {
+    fn at_start(tree: &'a Tree<T>) -> Position<'a, T> {
+        let mut position = Position { pending: Vec::new(), current: None, offset: 0 };
+        position.descend_left(tree.as_deref());
+        position.current = position.pending.pop();
+        position
+    }
+
+    fn at_end(tree: &'a Tree<T>) -> Position<'a, T> {
+        let mut position = Position { pending: Vec::new(), current: None, offset: 0 };
+        position.descend_right(tree.as_deref());
+        position.current = position.pending.pop();
+        position
+    }
+
+    /// A forward position at `index`.
+    fn at_index(tree: &'a Tree<T>, mut index: usize) -> Position<'a, T> {
+        let mut position = Position { pending: Vec::new(), current: None, offset: 0 };
+        let mut next = tree.as_ref();
+        while let Some(node) = next {
+            let left_size = size(&node.left);
+            if index < left_size {
+                position.pending.push(node);
+                next = node.left.as_ref();
+            } else if index < left_size + node.len {
+                position.current = Some(node);
+                position.offset = index - left_size;
+                return position;
+            } else {
+                index -= left_size + node.len;
+                next = node.right.as_ref();
+            }
+        }
+        position
+    }
+
+    fn descend_left(&mut self, mut node: Option<&'a Node<T>>) {
+        while let Some(n) = node {
+            self.pending.push(n);
+            node = n.left.as_deref();
+        }
+    }
+
+    fn descend_right(&mut self, mut node: Option<&'a Node<T>>) {
+        while let Some(n) = node {
+            self.pending.push(n);
+            node = n.right.as_deref();
+        }
+    }
+
+    fn next_run(&mut self) {
+        if let Some(node) = self.current {
+            self.descend_left(node.right.as_deref());
+        }
+        self.current = self.pending.pop();
+        self.offset = 0;
+    }
+
+    fn prev_run(&mut self) {
+        if let Some(node) = self.current {
+            self.descend_right(node.left.as_deref());
+        }
+        self.current = self.pending.pop();
+        self.offset = 0;
+    }
+}
+
+/// Immutable `RleTree` iterator over references of values.
+///
+/// Can be obtained from the [`iter`](struct.RleTree.html#method.iter) method.
+pub struct Iter<'a, T: 'a> {
+    root: &'a Tree<T>,
+    front: Position<'a, T>,
+    back: Position<'a, T>,
+    index: usize,
+    index_back: usize,
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.index_back {
+            return None
+        }
+        let node = self.front.current?;
+        self.index += 1;
+        self.front.offset += 1;
+        if self.front.offset == node.len {
+            self.front.next_run();
+        }
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.index_back - self.index;
+        (len, Some(len))
+    }
+
+    fn count(self) -> usize {
+        // thanks to the ExactSizeIterator impl
+        self.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.len() {
+            self.index = self.index_back;
+            return None
+        }
+
+        match self.front.current {
+            Some(node) if self.front.offset + n < node.len => self.front.offset += n,
+            // descend from the root instead of walking over the skipped runs
+            _ => self.front = Position::at_index(self.root, self.index + n),
+        }
+        self.index += n;
+        self.next()
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for Iter<'a, T> { }
+
+impl<'a, T: 'a> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.index == self.index_back {
+            return None
+        }
+        let node = self.back.current?;
+        self.index_back -= 1;
+        self.back.offset += 1;
+        if self.back.offset == node.len {
+            self.back.prev_run();
+        }
+        Some(&node.value)
+    }
+}
+
+/// Immutable `RleTree` iterator over runs.
+///
+/// Can be obtained from the [`runs`](struct.RleTree.html#method.runs) method.
+pub struct Runs<'a, T: 'a> {
+    position: Position<'a, T>,
+    remaining: usize,
+}
+
+impl<'a, T: 'a> Iterator for Runs<'a, T> {
+    type Item = Run<&'a T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.position.current?;
+        self.remaining -= 1;
+        self.position.next_run();
+        Some(Run { len: node.len, value: &node.value })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for Runs<'a, T> { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::same_as;
+
+    fn depth<T>(tree: &Tree<T>) -> usize {
+        tree.as_ref().map_or(0, |node| 1 + std::cmp::max(depth(&node.left), depth(&node.right)))
+    }
+
+    fn assert_canonical<T: PartialEq + fmt::Debug>(tree: &RleTree<T>) {
+        let runs: Vec<_> = tree.runs().collect();
+        assert_eq!(runs.len(), tree.runs_len());
+        assert_eq!(runs.iter().map(|r| r.len).sum::<usize>(), tree.len());
+        assert!(runs.iter().all(|r| r.len > 0));
+        assert!(runs.windows(2).all(|w| w[0].value != w[1].value), "{:?}", runs);
+    }
+
+    #[test]
+    fn same_as_vec() {
+        let mut v = vec![0; 100];
+        let mut tree = RleTree::from(&v[..]);
+        same_as(42, 2_000, &mut v, &mut tree, |rng, v, tree| {
+            let value = rng.below(3);
+            match rng.below(4) {
+                0 => {
+                    let index = rng.below(v.len() + 1);
+                    v.insert(index, value);
+                    tree.insert(index, value);
+                }
+                1 if !v.is_empty() => {
+                    let index = rng.below(v.len());
+                    assert_eq!(tree.remove(index), v.remove(index));
+                }
+                2 if !v.is_empty() => {
+                    let index = rng.below(v.len());
+                    v[index] = value;
+                    tree.set(index, value);
+                }
+                _ => {
+                    v.push(value);
+                    tree.push(value);
+                }
+            }
+        }, |v, tree| assert_eq!(tree.len(), v.len()));
+        assert_eq!(tree.to_vec(), v);
+        assert_eq!((0..v.len()).map(|i| tree[i]).collect::<Vec<_>>(), v);
+        assert_canonical(&tree);
+        assert_eq!(RleVec::from(tree), RleVec::from(&v[..]));
+    }
+
+    #[test]
+    fn stays_balanced() {
+        // breaking the same run over and over
+        let mut tree = RleTree::new();
+        tree.push_n(100_000, 0);
+        for i in 0..10_000 {
+            tree.set(i * 10 + 5, 1);
+        }
+        assert_eq!(tree.runs_len(), 20_001);
+        assert_canonical(&tree);
+        assert!(depth(&tree.root) < 100, "depth {}", depth(&tree.root));
+    }
+
+    #[test]
+    fn splicing() {
+        let mut tree = RleTree::from(&[1, 1, 2, 2, 3, 3][..]);
+        let removed = tree.splice(1..=4, vec![1, 3]);
+        assert_eq!(removed.to_vec(), vec![1, 2, 2, 3]);
+        assert_eq!(tree.to_vec(), vec![1, 1, 3, 3]);
+        assert_eq!(tree.runs_len(), 2);
+        assert_canonical(&tree);
+
+        let removed = tree.splice(.., Vec::new());
+        assert_eq!(removed.runs_len(), 2);
+        assert!(tree.is_empty());
+
+        let mut tree = RleTree::from(&[1, 2, 3][..]);
+        let mut tail = tree.split_off(3);
+        assert!(tail.is_empty());
+        tail = tree.split_off(0);
+        assert!(tree.is_empty());
+        tree.push(3);
+        tail.append(&mut tree);
+        assert_eq!(tail.to_vec(), vec![1, 2, 3, 3]);
+        assert_eq!(tail.last_run(), Some(Run { len: 2, value: &3 }));
+        assert_eq!(tail, RleTree::from(&[1, 2, 3, 3][..]));
+    }
+
+    #[test]
+    fn iterators() {
+        let v = vec![0,0,0,1,1,1,1,1,1,1,3,3,123,0,90,90,99];
+        let tree = v.iter().cloned().collect::<RleTree<_>>();
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), v);
+        assert_eq!(tree.iter().rev().cloned().collect::<Vec<_>>(), v.iter().rev().cloned().collect::<Vec<_>>());
+        assert_eq!(tree.iter().count(), 17);
+        assert_eq!(tree.starts(), vec![0,3,10,12,13,14,16]);
+        assert_eq!(tree.ends(), vec![2,9,11,12,13,15,16]);
+
+        for i in 0..v.len() {
+            assert_eq!(tree.iter().nth(i), v.get(i));
+            let mut iter = tree.iter();
+            iter.next();
+            assert_eq!(iter.nth(i), v.get(i + 1));
+            assert_eq!(iter.len(), v.len().saturating_sub(i + 2));
+        }
+
+        let mut iter = tree.iter();
+        assert_eq!(iter.next_back(), Some(&99));
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.nth(13), Some(&90));
+        assert_eq!(iter.next_back(), Some(&90));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.next(), None);
+
+        let empty = RleTree::<i32>::new();
+        assert_eq!(empty.iter().next(), None);
+        assert_eq!(empty.iter().next_back(), None);
+        assert_eq!(empty.runs().next(), None);
+        assert_eq!(empty.get(0), None);
+    }
+}